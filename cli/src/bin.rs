@@ -1,20 +1,53 @@
+mod client;
 mod commands;
 mod helpers;
 mod interactive;
 mod logging;
 mod models;
+mod renderer;
+mod router;
 mod utils;
 
-use clap::{Parser, Subcommand};
-use logging::{error::DaikokuResult, logger};
+use clap::{Parser, Subcommand, ValueEnum};
+use logging::{
+    error::{DaikokuResult, EXIT_CODES_HELP},
+    logger,
+};
 
 /// A fictional versioning CLI
 #[derive(Debug, Parser)] // requires `derive` feature
 #[command(name = "daikoku")]
 #[command(about = "Daikoku CLI", long_about = None, version = env!("CARGO_PKG_VERSION"))]
+#[command(after_help = EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// json prints the result of the command as JSON on stdout, progress is written on stderr
+    #[arg(
+        value_name = "OUTPUT",
+        long = "output",
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text
+    )]
+    output: OutputFormat,
+    /// never prompt: confirmations are accepted and commands needing a browser fail
+    #[arg(
+        long = "yes",
+        visible_alias = "non-interactive",
+        global = true,
+        env = "DAIKOKU_NON_INTERACTIVE"
+    )]
+    yes: bool,
+    /// name or path of the project to use, instead of the project containing the current directory
+    #[arg(value_name = "PROJECT", long = "project", global = true)]
+    project: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
 }
 
 #[derive(Debug, Subcommand)]
@@ -37,6 +70,15 @@ pub enum Commands {
         /// Enable/Disable cookie usage - really useful for testing authenticated pages
         #[arg(value_name = "AUTHENTICATION", short = 'a', long = "authentication")]
         authentication: Option<bool>,
+        /// render pages locally, without a Daikoku server, using the stub data of the fixtures file
+        #[arg(long = "offline")]
+        offline: bool,
+        /// fixtures file used by the offline mode, defaults to fixtures.json at the project root
+        #[arg(value_name = "FIXTURES", long = "fixtures", requires = "offline")]
+        fixtures: Option<String>,
+        /// serve the valid pages when some files of the project can't be read
+        #[arg(long = "skip-invalid")]
+        skip_invalid: bool,
     },
     /// Manage your environments representing your Daikoku servers
     Environments {
@@ -48,17 +90,87 @@ pub enum Commands {
         #[command(subcommand)]
         command: CmsCommands,
     },
+    /// show the differences between the local project and the pages stored on Daikoku
+    Diff {},
     /// ⚠️ synchronize projects file with Daikoku
     Push {
-        #[arg(value_name = "DRY_RUN", short = 'd', long = "dry_run")]
+        /// print the pages that would be added or modified, without pushing them
+        #[arg(
+            value_name = "DRY_RUN",
+            short = 'd',
+            long = "dry_run",
+            visible_alias = "dry-run",
+            num_args = 0..=1,
+            default_missing_value = "true"
+        )]
         dry_run: Option<bool>,
         #[arg(value_name = "FILE_PATH", short = 'f', long = "file_path")]
         file_path: Option<String>,
+        /// push all pages, including those unchanged since the last push to the environment
+        #[arg(long = "full")]
+        full: bool,
+        /// push the valid pages when some files of the project can't be read
+        #[arg(long = "skip-invalid")]
+        skip_invalid: bool,
+        /// delete the remote pages pushed by the CLI which have no local file anymore
+        #[arg(long = "prune", conflicts_with_all = ["file_path", "skip_invalid"])]
+        prune: bool,
+        /// push the pages published on Daikoku since they were pulled, overwriting their changes
+        #[arg(long = "force")]
+        force: bool,
     },
     Pull {
         #[command(subcommand)]
         command: PullCommands,
     },
+    /// list the pushes recorded in the project, from the most recent
+    History {},
+    /// push again the pages of an environment as they were after a previous push
+    Rollback {
+        /// id of the push, see daikoku history
+        #[arg(value_name = "ID")]
+        id: String,
+    },
+    /// copy the pages of an environment to another one, after showing the differences
+    Promote {
+        /// environment to read the pages from
+        #[arg(value_name = "FROM", long = "from")]
+        from: String,
+        /// environment to write the pages to
+        #[arg(value_name = "TO", long = "to")]
+        to: String,
+        /// copy the assets too
+        #[arg(long = "assets")]
+        assets: bool,
+        /// copy the mail pages too
+        #[arg(long = "mails")]
+        mails: bool,
+        /// copy the style, script and color theme of the tenant too
+        #[arg(long = "customization")]
+        customization: bool,
+        /// print the differences without copying anything
+        #[arg(
+            value_name = "DRY_RUN",
+            short = 'd',
+            long = "dry_run",
+            visible_alias = "dry-run",
+            num_args = 0..=1,
+            default_missing_value = "true"
+        )]
+        dry_run: Option<bool>,
+    },
+    /// save the pages, mails, apis pages, customization and assets of the tenant to a zip
+    Backup {
+        /// path of the zip to create
+        #[arg(value_name = "OUT", short = 'o', long = "out")]
+        out: String,
+    },
+    /// send the content of a backup to the default environment
+    Restore {
+        /// path of the zip created by daikoku backup
+        #[arg(value_name = "ARCHIVE")]
+        archive: String,
+    },
     /// Manage your CMS assets
     Assets {
         #[command(subcommand)]
@@ -82,6 +194,21 @@ pub enum EnvironmentsCommands {
         apikey: String,
         #[arg(value_name = "OVERWRITE", long = "overwrite", required = false)]
         overwrite: Option<bool>,
+        /// PEM bundle of the authorities to trust, relative to the project
+        #[arg(value_name = "CA_FILE", long = "ca-file")]
+        ca_file: Option<String>,
+        /// PEM certificate presented to a server requiring mutual TLS
+        #[arg(value_name = "CLIENT_CERT", long = "client-cert", requires = "client_key")]
+        client_cert: Option<String>,
+        /// PEM (PKCS #8) key of the client certificate, saved in the .secrets file
+        #[arg(value_name = "CLIENT_KEY", long = "client-key", requires = "client_cert")]
+        client_key: Option<String>,
+        /// accept invalid certificates, for local development only
+        #[arg(long = "insecure", default_value = "false")]
+        insecure: bool,
+        /// URL of the proxy used to reach the server, none to ignore the proxy variables
+        #[arg(value_name = "PROXY", long = "proxy")]
+        proxy: Option<String>,
     },
     /// update default environment
     Config {
@@ -205,6 +332,28 @@ pub enum PullCommands {
     },
     Mails {},
     Customization {},
+    /// pull all CMS pages into the current project
+    Pages {
+        /// what to do with local files that differ from the remote pages
+        #[arg(
+            value_name = "CONFLICT",
+            short = 'c',
+            long = "conflict",
+            value_enum,
+            default_value_t = ConflictStrategy::Skip
+        )]
+        conflict: ConflictStrategy,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConflictStrategy {
+    /// keep local files untouched
+    Skip,
+    /// replace local files with the remote pages
+    Overwrite,
+    /// write the remote pages next to local files, with a .remote extension
+    Remote,
 }
 
 #[derive(Debug, Subcommand)]
@@ -226,12 +375,47 @@ async fn process(command: Commands) -> DaikokuResult<()> {
         Commands::Watch {
             environment,
             authentication,
-        } => commands::watch::run(environment, authentication).await,
+            offline,
+            fixtures,
+            skip_invalid,
+        } => {
+            commands::watch::run(environment, authentication, offline, fixtures, skip_invalid).await
+        }
         Commands::Environments { command } => commands::environments::run(command).await,
         Commands::Cms { command } => commands::cms::run(command).await,
         Commands::Login {} => commands::login::run().await,
         Commands::Pull { command } => commands::pull::run(command).await,
-        Commands::Push { dry_run, file_path } => commands::push::run(dry_run, file_path).await,
+        Commands::Diff {} => commands::diff::run().await,
+        Commands::Push {
+            dry_run,
+            file_path,
+            full,
+            skip_invalid,
+            prune,
+            force,
+        } => commands::push::run(dry_run, file_path, full, skip_invalid, prune, force).await,
+        Commands::History {} => commands::history::list().await,
+        Commands::Rollback { id } => commands::history::rollback(id).await,
+        Commands::Promote {
+            from,
+            to,
+            assets,
+            mails,
+            customization,
+            dry_run,
+        } => {
+            commands::promote::run(
+                from,
+                to,
+                assets,
+                mails,
+                customization,
+                dry_run.unwrap_or(false),
+            )
+            .await
+        }
+        Commands::Backup { out } => commands::backup::backup(out).await,
+        Commands::Restore { archive } => commands::backup::restore(archive).await,
         Commands::Assets { command } => commands::assets::run(command).await,
         Commands::Generate { command } => commands::generate::run(command).await,
     }
@@ -241,12 +425,24 @@ async fn process(command: Commands) -> DaikokuResult<()> {
 async fn main() {
     let args = Cli::parse();
 
+    logger::set_output_format(args.output);
+    interactive::set_non_interactive(args.yes);
+    commands::cms::set_project(args.project);
+
     let out = process(args.command).await;
 
     if let Err(e) = out {
+        logger::discard_json();
+        logger::flush_json(&serde_json::json!({
+            "success": false,
+            "error": e.to_string().trim(),
+            "exit_code": e.exit_code(),
+        }));
         logger::error(format!("{}", e));
-        std::process::exit(1);
+        std::process::exit(e.exit_code());
     }
 
+    logger::flush_json(&serde_json::json!({ "success": true }));
+
     std::process::exit(0);
 }
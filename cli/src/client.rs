@@ -0,0 +1,437 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use hyper::header;
+use once_cell::sync::Lazy;
+use reqwest::{
+    redirect::Policy, Certificate, Identity, Method, NoProxy, Proxy, RequestBuilder, Response,
+    StatusCode,
+};
+
+use crate::{
+    commands::{
+        cms::get_default_project,
+        environments::{
+            get_default_environment, read_apikey_from_secrets, read_variable, Environment,
+        },
+    },
+    logging::{
+        error::{DaikokuCliError, DaikokuResult},
+        logger,
+    },
+};
+
+/// variables taking precedence over the timeout, connect_timeout, retries, TLS and proxy keys of
+/// the environment
+pub(crate) const DAIKOKU_TIMEOUT: &str = "DAIKOKU_TIMEOUT";
+pub(crate) const DAIKOKU_CONNECT_TIMEOUT: &str = "DAIKOKU_CONNECT_TIMEOUT";
+pub(crate) const DAIKOKU_RETRIES: &str = "DAIKOKU_RETRIES";
+pub(crate) const DAIKOKU_CA_FILE: &str = "DAIKOKU_CA_FILE";
+pub(crate) const DAIKOKU_CLIENT_CERT: &str = "DAIKOKU_CLIENT_CERT";
+pub(crate) const DAIKOKU_CLIENT_KEY: &str = "DAIKOKU_CLIENT_KEY";
+pub(crate) const DAIKOKU_INSECURE: &str = "DAIKOKU_INSECURE";
+pub(crate) const DAIKOKU_PROXY: &str = "DAIKOKU_PROXY";
+
+/// proxy value ignoring the HTTP_PROXY, HTTPS_PROXY and ALL_PROXY variables
+const NO_PROXY: &str = "none";
+
+const DEFAULT_TIMEOUT: u64 = 120;
+const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+const DEFAULT_RETRIES: u32 = 3;
+
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(10);
+const RETRY_AFTER_MAX: Duration = Duration::from_secs(30);
+
+/// settings of the underlying connection pool, clients with the same settings share it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ClientSettings {
+    timeout: Duration,
+    connect_timeout: Duration,
+    ca_file: Option<PathBuf>,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
+    insecure: bool,
+    proxy: Option<String>,
+}
+
+static CLIENTS: Lazy<Mutex<HashMap<ClientSettings, reqwest::Client>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// HTTP client of a Daikoku environment : sets the Host and authorization headers, applies the
+/// timeouts of the environment and retries the requests failing on a transient error
+#[derive(Debug, Clone)]
+pub(crate) struct DaikokuClient {
+    client: reqwest::Client,
+    server: String,
+    host: String,
+    apikey: Option<String>,
+    retries: u32,
+}
+
+fn setting<T: std::str::FromStr>(
+    variable: &str,
+    value: &Option<String>,
+    default: T,
+) -> DaikokuResult<T> {
+    match read_variable(variable).or(value.clone()) {
+        None => Ok(default),
+        Some(value) => value.trim().parse::<T>().map_err(|_| {
+            DaikokuCliError::Configuration(format!("invalid value {} for {}", value, variable))
+        }),
+    }
+}
+
+/// files of the environment are relative to the default project, when there is one
+fn file_setting(variable: &str, value: &Option<String>) -> DaikokuResult<Option<PathBuf>> {
+    match read_variable(variable).or(value.clone()) {
+        None => Ok(None),
+        Some(path) if PathBuf::from(&path).is_absolute() => Ok(Some(PathBuf::from(path))),
+        Some(path) => Ok(Some(
+            get_default_project()
+                .map(|project| PathBuf::from(project.path).join(&path))
+                .unwrap_or(PathBuf::from(path)),
+        )),
+    }
+}
+
+fn read_file(path: &Path, kind: &str) -> DaikokuResult<Vec<u8>> {
+    fs::read(path).map_err(|err| {
+        DaikokuCliError::FileSystem(format!(
+            "failed to read the {} {} : {}",
+            kind,
+            path.display(),
+            err
+        ))
+    })
+}
+
+fn tls_error(kind: &str, path: &Path, err: reqwest::Error) -> DaikokuCliError {
+    DaikokuCliError::Configuration(format!("invalid {} {} : {}", kind, path.display(), err))
+}
+
+impl ClientSettings {
+    fn build(&self) -> DaikokuResult<reqwest::Client> {
+        // redirections are answers of Daikoku, like the 303 of an expired session
+        let mut builder = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout)
+            .redirect(Policy::none());
+
+        if let Some(ca_file) = &self.ca_file {
+            for certificate in Certificate::from_pem_bundle(&read_file(ca_file, "CA file")?)
+                .map_err(|err| tls_error("CA file", ca_file, err))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        builder = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => builder.identity(
+                Identity::from_pkcs8_pem(
+                    &read_file(cert, "client certificate")?,
+                    &read_file(key, "client key")?,
+                )
+                .map_err(|err| tls_error("client certificate", cert, err))?,
+            ),
+            (None, None) => builder,
+            _ => {
+                return Err(DaikokuCliError::Configuration(
+                    "client_cert and client_key must be set together".to_string(),
+                ))
+            }
+        };
+
+        // without a proxy setting, the HTTP_PROXY, HTTPS_PROXY, ALL_PROXY and NO_PROXY variables
+        // are used
+        builder = match self.proxy.as_deref() {
+            None => builder,
+            Some(NO_PROXY) => builder.no_proxy(),
+            Some(proxy) => builder.proxy(
+                Proxy::all(proxy)
+                    .map_err(|err| {
+                        DaikokuCliError::Configuration(format!("invalid proxy {} : {}", proxy, err))
+                    })?
+                    .no_proxy(NoProxy::from_env()),
+            ),
+        };
+
+        if self.insecure {
+            builder = builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        builder.build().map_err(|err| {
+            DaikokuCliError::Configuration(format!("failed to create the HTTP client : {}", err))
+        })
+    }
+}
+
+impl DaikokuClient {
+    pub(crate) fn new(environment: &Environment) -> DaikokuResult<DaikokuClient> {
+        let settings = ClientSettings {
+            timeout: Duration::from_secs(setting(
+                DAIKOKU_TIMEOUT,
+                &environment.timeout,
+                DEFAULT_TIMEOUT,
+            )?),
+            connect_timeout: Duration::from_secs(setting(
+                DAIKOKU_CONNECT_TIMEOUT,
+                &environment.connect_timeout,
+                DEFAULT_CONNECT_TIMEOUT,
+            )?),
+            ca_file: file_setting(DAIKOKU_CA_FILE, &environment.ca_file)?,
+            client_cert: file_setting(DAIKOKU_CLIENT_CERT, &environment.client_cert)?,
+            client_key: file_setting(DAIKOKU_CLIENT_KEY, &environment.client_key)?,
+            insecure: setting(DAIKOKU_INSECURE, &environment.insecure, false)?,
+            proxy: read_variable(DAIKOKU_PROXY)
+                .or(environment.proxy.clone())
+                .map(|proxy| proxy.trim().to_string())
+                .filter(|proxy| !proxy.is_empty()),
+        };
+
+        if settings.insecure {
+            warn_insecure(&environment.server);
+        }
+
+        let retries = setting(DAIKOKU_RETRIES, &environment.retries, DEFAULT_RETRIES)?;
+
+        let mut clients = CLIENTS.lock().unwrap();
+
+        let client = match clients.get(&settings) {
+            Some(client) => client.clone(),
+            None => {
+                let client = settings.build()?;
+                clients.insert(settings, client.clone());
+                client
+            }
+        };
+
+        Ok(DaikokuClient {
+            client,
+            server: environment.server.clone(),
+            host: environment
+                .server
+                .replace("http://", "")
+                .replace("https://", ""),
+            apikey: environment.apikey.clone(),
+            retries,
+        })
+    }
+
+    /// client of the default environment, authenticated with its apikey
+    pub(crate) fn default_environment() -> DaikokuResult<DaikokuClient> {
+        let mut environment = get_default_environment()?;
+        environment.apikey = Some(read_apikey_from_secrets(true)?);
+
+        DaikokuClient::new(&environment)
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.server, path)
+    }
+
+    pub(crate) fn cms_api_url(&self, path: &str) -> String {
+        format!("{}/cms-api{}", self.server, path)
+    }
+
+    pub(crate) fn request(&self, method: Method, url: String) -> RequestBuilder {
+        let builder = self
+            .client
+            .request(method, url)
+            .header(header::HOST, &self.host);
+
+        match &self.apikey {
+            Some(apikey) => builder.header(header::AUTHORIZATION, format!("Basic {}", apikey)),
+            None => builder,
+        }
+    }
+
+    pub(crate) fn get(&self, url: String) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub(crate) fn post(&self, url: String) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub(crate) fn delete(&self, url: String) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    pub(crate) fn head(&self, url: String) -> RequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+
+    /// sends the request, and sends it again with an exponential backoff while Daikoku answers
+    /// with a 5xx or 429 status or while the connection can't be established or is reset.
+    ///
+    /// POST and PATCH requests aren't idempotent : they are only sent again when Daikoku
+    /// refused them with a 429 or 503 status or when the connection failed before they were
+    /// sent, unless they are sent with `send_idempotent`
+    pub(crate) async fn send(&self, request: RequestBuilder) -> DaikokuResult<Response> {
+        self.send_with_retries(request, false).await
+    }
+
+    /// sends a POST or PATCH request which can be processed twice by Daikoku without side
+    /// effects, retrying it like a GET request
+    pub(crate) async fn send_idempotent(&self, request: RequestBuilder) -> DaikokuResult<Response> {
+        self.send_with_retries(request, true).await
+    }
+
+    async fn send_with_retries(
+        &self,
+        request: RequestBuilder,
+        always_idempotent: bool,
+    ) -> DaikokuResult<Response> {
+        let (client, request) = request.build_split();
+        let request = request.map_err(|err| DaikokuCliError::Network(err.to_string()))?;
+        let idempotent = always_idempotent || is_idempotent(request.method());
+        let mut attempt: u32 = 0;
+
+        loop {
+            // requests with a streamed body can't be sent twice
+            let Some(current) = request.try_clone() else {
+                return client
+                    .execute(request)
+                    .await
+                    .map_err(|err| DaikokuCliError::Network(err.to_string()));
+            };
+
+            let delay = match client.execute(current).await {
+                Ok(response)
+                    if is_retryable_status(response.status(), idempotent)
+                        && attempt < self.retries =>
+                {
+                    let delay = retry_after(&response).unwrap_or(backoff(attempt));
+                    logger::info(format!(
+                        "<yellow>Retrying</> Daikoku answered {}, next attempt in {}ms",
+                        response.status().as_u16(),
+                        delay.as_millis()
+                    ));
+                    delay
+                }
+                Ok(response) => return Ok(response),
+                Err(err) if is_retryable_error(&err, idempotent) && attempt < self.retries => {
+                    let delay = backoff(attempt);
+                    logger::info(format!(
+                        "<yellow>Retrying</> {}, next attempt in {}ms",
+                        err,
+                        delay.as_millis()
+                    ));
+                    delay
+                }
+                Err(err) => return Err(DaikokuCliError::Network(err.to_string())),
+            };
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// error of a response with an unexpected status, with the message of the Daikoku error body
+pub(crate) async fn unexpected_status(method: Method, response: Response) -> DaikokuCliError {
+    let status = response.status().as_u16();
+    let url = response.url().to_string();
+    let body = response.bytes().await.unwrap_or_default();
+
+    DaikokuCliError::http(status, method.as_str(), &url, &body)
+}
+
+static INSECURE_WARNED: AtomicBool = AtomicBool::new(false);
+
+fn warn_insecure(server: &str) {
+    if !INSECURE_WARNED.swap(true, Ordering::SeqCst) {
+        logger::error(format!(
+            "TLS certificates of {} are not verified, never use insecure outside of local development",
+            server
+        ));
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    !matches!(*method, Method::POST | Method::PATCH)
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// a 429 or 503 status means that Daikoku didn't process the request, which can always be sent
+/// again
+fn is_retryable_status(status: StatusCode, idempotent: bool) -> bool {
+    if idempotent {
+        is_transient_status(status)
+    } else {
+        status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+/// a request which isn't idempotent may have been processed by Daikoku unless the connection
+/// couldn't be established
+fn is_retryable_error(err: &reqwest::Error, idempotent: bool) -> bool {
+    if idempotent {
+        is_transient_error(err)
+    } else {
+        err.is_connect()
+    }
+}
+
+fn is_transient_error(err: &reqwest::Error) -> bool {
+    if err.is_connect() || err.is_timeout() {
+        return true;
+    }
+
+    let mut source = err.source();
+
+    while let Some(cause) = source {
+        if let Some(io_error) = cause.downcast_ref::<std::io::Error>() {
+            if matches!(
+                io_error.kind(),
+                std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+
+        if let Some(hyper_error) = cause.downcast_ref::<hyper::Error>() {
+            if hyper_error.is_incomplete_message() || hyper_error.is_canceled() {
+                return true;
+            }
+        }
+
+        source = cause.source();
+    }
+
+    false
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(BACKOFF_MAX)
+}
+
+/// delay asked by Daikoku, in seconds, before sending the request again
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|seconds| Duration::from_secs(seconds).min(RETRY_AFTER_MAX))
+}
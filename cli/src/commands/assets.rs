@@ -2,30 +2,28 @@ use std::{
     fs::{self, File},
     io::Read,
     path::{Path, PathBuf},
-    str::FromStr,
 };
 
 use crate::{
+    client::{unexpected_status, DaikokuClient},
     helpers::daikoku_cms_api_post,
     logging::{
         error::{DaikokuCliError, DaikokuResult},
         logger,
     },
+    models::project_manifest::ProjectManifest,
     utils::absolute_path,
     AssetsCommands,
 };
 
 use bytes::Bytes;
 
-use hyper::header;
 use mime_guess::mime;
+use reqwest::Method;
 use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
-use super::{
-    cms::{self, get_default_project},
-    environments::{get_default_environment, read_apikey_from_secrets},
-};
+use super::cms::{self, get_default_project};
 
 #[derive(Deserialize, Serialize, Debug)]
 struct Asset {
@@ -64,41 +62,26 @@ pub(crate) async fn run(command: AssetsCommands) -> DaikokuResult<()> {
 }
 
 async fn exists(filename: String) -> DaikokuResult<()> {
-    let environment = get_default_environment()?;
-
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
-
-    let apikey = read_apikey_from_secrets(true)?;
+    let client = DaikokuClient::default_environment()?;
 
-    let url: String = format!(
-        "{}/tenant-assets/{}",
-        environment.server,
-        slug::slugify(filename.clone()),
-    );
+    let url: String = client.url(&format!("/tenant-assets/{}", slug::slugify(filename.clone())));
 
-    let resp = reqwest::Client::new()
-        .head(url)
-        .header(header::HOST, host)
-        .header(header::AUTHORIZATION, format!("Basic {}", apikey))
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    let resp = client.send(client.head(url)).await?;
 
     let status = resp.status().as_u16();
 
     if status == 303 {
-        Err(DaikokuCliError::DaikokuStrError(
+        Err(DaikokuCliError::Authentication(
             "Whoops, your session has expired. daikoku login is required".to_string(),
         ))
-    } else if status != 404 {
-        Err(DaikokuCliError::DaikokuStrError(
+    } else if status == 404 {
+        Ok(())
+    } else if status < 300 {
+        Err(DaikokuCliError::Conflict(
             "resource already exists".to_string(),
         ))
     } else {
-        Ok(())
+        Err(unexpected_status(Method::HEAD, resp).await)
     }
 }
 
@@ -117,21 +100,12 @@ async fn add(
     })
     .await?;
 
-    let url: String = format!(
-        "/tenant-assets?filename={}&title={}&desc={}&slug={}",
-        filename.clone(),
-        title,
-        desc,
-        slug.unwrap_or("".to_string())
-    );
-
     let project = get_default_project()?;
 
-    let filepath = PathBuf::from_str(&project.path)
-        .unwrap()
-        .join("assets")
+    let filepath = ProjectManifest::load(&PathBuf::from(&project.path))?
+        .assets()
         .join(path.unwrap_or("".to_string()))
-        .join(filename);
+        .join(&filename);
     let mut file =
         File::open(filepath.clone()).map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?;
 
@@ -144,7 +118,24 @@ async fn add(
         .first()
         .unwrap_or(mime::APPLICATION_OCTET_STREAM);
 
-    let _ = daikoku_cms_api_post(&url, Bytes::from(contents), false, Some(content_type)).await?;
+    let client = DaikokuClient::default_environment()?;
+
+    let builder = client
+        .post(client.cms_api_url("/tenant-assets"))
+        .query(&[
+            ("filename", filename.as_str()),
+            ("title", title.as_str()),
+            ("desc", desc.as_str()),
+            ("slug", slug.as_deref().unwrap_or("")),
+        ])
+        .header("Asset-Content-Type", content_type.to_string())
+        .body(contents);
+
+    let resp = client.send(builder).await?;
+
+    if resp.status().as_u16() >= 300 {
+        return Err(unexpected_status(Method::POST, resp).await);
+    }
 
     logger::success("New asset has been pushed".to_string());
 
@@ -154,28 +145,14 @@ async fn add(
 async fn remove(filename: String, path: Option<String>, slug: Option<String>) -> DaikokuResult<()> {
     logger::loading(format!("<yellow>Removing</> {} asset", filename));
 
-    let environment = get_default_environment()?;
-
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
-
-    let apikey = read_apikey_from_secrets(true)?;
+    let client = DaikokuClient::default_environment()?;
 
-    let url: String = format!(
-        "{}/cms-api/tenant-assets/{}",
-        environment.server,
+    let url: String = client.cms_api_url(&format!(
+        "/tenant-assets/{}",
         slug.unwrap_or(slug::slugify(filename.clone()))
-    );
+    ));
 
-    let req = reqwest::Client::new()
-        .delete(url)
-        .header(header::HOST, host)
-        .header(header::AUTHORIZATION, format!("Basic {}", apikey))
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    let req = client.send(client.delete(url)).await?;
 
     let status = req.status().as_u16();
 
@@ -183,18 +160,14 @@ async fn remove(filename: String, path: Option<String>, slug: Option<String>) ->
         let project = get_default_project()?;
 
         fs::remove_file(
-            PathBuf::from_str(&project.path)
-                .unwrap()
-                .join("assets")
+            ProjectManifest::load(&PathBuf::from(&project.path))?
+                .assets()
                 .join(path.unwrap_or("".to_string()))
                 .join(filename),
         )
         .map_err(|_err| DaikokuCliError::FileSystem("failed to remove local file".to_string()))
     } else {
-        Err(DaikokuCliError::DaikokuStrError(format!(
-            "failed to reach the Daikoku server {}",
-            status
-        )))
+        Err(unexpected_status(Method::DELETE, req).await)
     }
 }
 
@@ -203,24 +176,11 @@ async fn list() -> DaikokuResult<()> {
         "<yellow>Retrieving</> assets, limited to those identified by slugs."
     ));
 
-    let environment = get_default_environment()?;
-
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
+    let client = DaikokuClient::default_environment()?;
 
-    let apikey = read_apikey_from_secrets(true)?;
+    let url: String = client.cms_api_url("/tenant-assets/slugified");
 
-    let url: String = format!("{}/cms-api/tenant-assets/slugified", environment.server);
-
-    let req = reqwest::Client::new()
-        .get(url)
-        .header(header::HOST, host)
-        .header(header::AUTHORIZATION, format!("Basic {}", apikey))
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    let req = client.send(client.get(url)).await?;
 
     let status = req.status().as_u16();
 
@@ -228,7 +188,7 @@ async fn list() -> DaikokuResult<()> {
         let bytes = req
             .bytes()
             .await
-            .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+            .map_err(|err| DaikokuCliError::Network(err.to_string()))?;
 
         let assets: String = String::from_utf8(bytes.to_vec()).map_err(|_err| {
             DaikokuCliError::ParsingError("failed to convert assets body".to_string())
@@ -246,14 +206,15 @@ async fn list() -> DaikokuResult<()> {
             logger::println("no assets found".to_string());
         }
 
+        logger::json(&serde_json::json!({
+            "assets": assets.iter().map(|asset| &asset.slug).collect::<Vec<&String>>()
+        }));
+
         logger::success("".to_string());
 
         Ok(())
     } else {
-        Err(DaikokuCliError::DaikokuStrError(format!(
-            "failed to reach the Daikoku server {}",
-            status
-        )))
+        Err(unexpected_status(Method::GET, req).await)
     }
 }
 
@@ -262,10 +223,17 @@ async fn sync() -> DaikokuResult<()> {
 
     let project = cms::get_default_project()?;
 
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
+
     let mut pages: Vec<Asset> = Vec::new();
 
-    for entry in WalkDir::new(PathBuf::from(project.path).join("assets"))
+    for entry in WalkDir::new(project_manifest.assets())
         .into_iter()
+        .filter_entry(|entry| {
+            !project_manifest
+                .ignore
+                .is_ignored(entry.path(), entry.file_type().is_dir())
+        })
         .filter_map(Result::ok)
     {
         let f_name = String::from(entry.file_name().to_string_lossy());
@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::PathBuf,
+};
+
+use bytes::Bytes;
+use mime_guess::Mime;
+use serde::{Deserialize, Serialize};
+use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+use crate::{
+    helpers::{
+        bytes_to_struct, bytes_to_vec_of_struct, map_error_to_filesystem_error,
+        raw_daikoku_cms_api_get,
+    },
+    interactive::confirm,
+    logging::{
+        error::{DaikokuCliError, DaikokuResult},
+        logger,
+    },
+    models::{
+        folder::{read_sources_and_daikoku_metadata, CmsFile},
+        history::PushSnapshot,
+        project_manifest::ProjectManifest,
+    },
+};
+
+use super::{
+    cms::{
+        self, create_api_folder, create_mail_folder, Api, CmsPage, IntlTranslationBody, EXCLUDE_API,
+    },
+    environments::{get_default_environment, read_apikey_from_secrets, Environment},
+    promote::{customization_to_files, download_asset, fetch_assets, page_to_file, upload_asset},
+    push::sync_pages,
+};
+
+/// version of the archive layout, increased when a restore can't read older archives anymore
+const BACKUP_VERSION: u32 = 1;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const PAGES_ENTRY: &str = "pages.json";
+const TENANT_MAILS_ENTRY: &str = "mails/tenant.mail.template.json";
+const MAILS_ENTRY: &str = "mails/mail.json";
+const APIS_ENTRY: &str = "apis.json";
+const CUSTOMIZATION_ENTRY: &str = "customization.json";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupManifest {
+    version: u32,
+    cli_version: String,
+    environment: String,
+    server: String,
+    /// milliseconds since the epoch
+    timestamp: i64,
+    pages: usize,
+    apis: usize,
+    assets: Vec<BackupAsset>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct BackupAsset {
+    slug: String,
+    content_type: Option<String>,
+    /// filename, title and description given when the asset was uploaded
+    #[serde(default)]
+    meta: HashMap<String, String>,
+}
+
+fn asset_entry(slug: &str) -> String {
+    format!("assets/{}", slug)
+}
+
+fn default_environment_with_apikey() -> DaikokuResult<Environment> {
+    let mut environment = get_default_environment()?;
+    environment.apikey = Some(read_apikey_from_secrets(true)?);
+    Ok(environment)
+}
+
+/// Downloads the pages, mails, apis pages, customization and assets of the tenant into a zip
+pub(crate) async fn backup(out: String) -> DaikokuResult<()> {
+    let environment = default_environment_with_apikey()?;
+
+    logger::loading(format!("<yellow>Backing up</> {}", environment.name));
+
+    let pages = raw_daikoku_cms_api_get("/pages", &environment).await?;
+    let tenant_mails = raw_daikoku_cms_api_get(
+        "/translations/_mail?domain=tenant.mail.template",
+        &environment,
+    )
+    .await?;
+    let mails = raw_daikoku_cms_api_get("/translations/_mail?domain=mail", &environment).await?;
+    let apis = raw_daikoku_cms_api_get(
+        "/apis?fields=_id,_humanReadableId,header,description",
+        &environment,
+    )
+    .await?;
+    let customization = raw_daikoku_cms_api_get("/tenant/customization", &environment).await?;
+
+    let mut assets: Vec<(BackupAsset, Bytes)> = Vec::new();
+    for asset in fetch_assets(&environment).await? {
+        let slug = asset.slug.unwrap_or_default();
+        let (content, content_type) = download_asset(&environment, &slug).await?;
+        logger::indent_println(format!("<green>+ asset</> {}", slug));
+        assets.push((
+            BackupAsset {
+                slug,
+                content_type: content_type.map(|mime| mime.to_string()),
+                meta: asset.meta,
+            },
+            content,
+        ));
+    }
+
+    logger::done();
+
+    let pages_count = bytes_to_vec_of_struct::<CmsPage>(pages.clone())?.len();
+    let apis_count = bytes_to_vec_of_struct::<Api>(apis.clone())?.len();
+
+    let manifest = BackupManifest {
+        version: BACKUP_VERSION,
+        cli_version: env!("CARGO_PKG_VERSION").to_string(),
+        environment: environment.name.clone(),
+        server: environment.server.clone(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        pages: pages_count,
+        apis: apis_count,
+        assets: vec![],
+    };
+
+    let mut zip =
+        ZipWriter::new(File::create(&out).map_err(|err| map_error_to_filesystem_error(err, &out))?);
+
+    let mut write_entry = |name: &str, content: &[u8]| -> DaikokuResult<()> {
+        zip.start_file(name, SimpleFileOptions::default())
+            .map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?;
+        zip.write_all(content)
+            .map_err(|err| map_error_to_filesystem_error(err, name))
+    };
+
+    write_entry(PAGES_ENTRY, &pages)?;
+    write_entry(TENANT_MAILS_ENTRY, &tenant_mails)?;
+    write_entry(MAILS_ENTRY, &mails)?;
+    write_entry(APIS_ENTRY, &apis)?;
+    write_entry(CUSTOMIZATION_ENTRY, &customization)?;
+
+    for (asset, content) in &assets {
+        write_entry(&asset_entry(&asset.slug), content)?;
+    }
+
+    let manifest = BackupManifest {
+        assets: assets.into_iter().map(|(asset, _)| asset).collect(),
+        ..manifest
+    };
+
+    write_entry(
+        MANIFEST_ENTRY,
+        &serde_json::to_vec_pretty(&manifest)
+            .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?,
+    )?;
+
+    zip.finish()
+        .map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?;
+
+    logger::success(format!(
+        "{} page(s), {} api(s) and {} asset(s) of {} saved to {}",
+        manifest.pages,
+        manifest.apis,
+        manifest.assets.len(),
+        manifest.environment,
+        out
+    ));
+    logger::json(&serde_json::json!({
+        "archive": out,
+        "manifest": manifest,
+    }));
+
+    Ok(())
+}
+
+fn read_entry(archive: &mut ZipArchive<File>, name: &str) -> DaikokuResult<Vec<u8>> {
+    let mut entry = archive.by_name(name).map_err(|err| {
+        DaikokuCliError::FileSystem(format!("{} is missing from the backup : {}", name, err))
+    })?;
+
+    let mut content = Vec::new();
+    entry
+        .read_to_end(&mut content)
+        .map_err(|err| map_error_to_filesystem_error(err, name))?;
+
+    Ok(content)
+}
+
+/// Mails and apis pages written to a temporary project like a pull does, then read like a push
+/// does, so that they are sent with the same paths and ids
+fn mails_and_apis_pages(
+    tenant_mails: IntlTranslationBody,
+    mails: IntlTranslationBody,
+    apis: Vec<Api>,
+) -> DaikokuResult<Vec<CmsFile>> {
+    let path = std::env::temp_dir().join(format!("daikoku-restore-{}", uuid::Uuid::new_v4()));
+
+    fs::create_dir_all(&path).map_err(|err| map_error_to_filesystem_error(err, "restore"))?;
+
+    let pages = (|| {
+        let manifest = ProjectManifest::load(&path)?;
+
+        create_mail_folder(tenant_mails, &manifest, true)?;
+        create_mail_folder(mails, &manifest, false)?;
+        create_api_folder(
+            apis.into_iter()
+                .filter(|api| !EXCLUDE_API.contains(&api._id.as_str()))
+                .collect(),
+            &manifest,
+        )?;
+
+        read_sources_and_daikoku_metadata(&manifest).check(false)
+    })();
+
+    let _ = fs::remove_dir_all(&path);
+
+    pages
+}
+
+/// Sends the content of a backup to the default environment, replacing the pages and assets with
+/// the same paths and slugs
+pub(crate) async fn restore(archive_path: String) -> DaikokuResult<()> {
+    let file = File::open(PathBuf::from(&archive_path)).map_err(|err| {
+        DaikokuCliError::FileSystem(format!("failed to open {} : {}", archive_path, err))
+    })?;
+
+    let mut archive = ZipArchive::new(file).map_err(|err| {
+        DaikokuCliError::FileSystem(format!("{} is not a backup : {}", archive_path, err))
+    })?;
+
+    let manifest: BackupManifest = bytes_to_struct(read_entry(&mut archive, MANIFEST_ENTRY)?)?;
+
+    if manifest.version != BACKUP_VERSION {
+        return Err(DaikokuCliError::Configuration(format!(
+            "unsupported backup version {}, this CLI restores version {}",
+            manifest.version, BACKUP_VERSION
+        )));
+    }
+
+    let environment = default_environment_with_apikey()?;
+
+    let mut pages = mails_and_apis_pages(
+        bytes_to_struct(read_entry(&mut archive, TENANT_MAILS_ENTRY)?)?,
+        bytes_to_struct(read_entry(&mut archive, MAILS_ENTRY)?)?,
+        bytes_to_vec_of_struct(read_entry(&mut archive, APIS_ENTRY)?)?,
+    )?;
+    pages.extend(
+        bytes_to_vec_of_struct::<CmsPage>(read_entry(&mut archive, PAGES_ENTRY)?)?
+            .into_iter()
+            .map(page_to_file),
+    );
+    pages.extend(customization_to_files(bytes_to_vec_of_struct(read_entry(
+        &mut archive,
+        CUSTOMIZATION_ENTRY,
+    )?)?));
+
+    // the pages stored on the tenant win over the ones rebuilt from the mails and apis
+    let mut by_path: HashMap<String, CmsFile> = HashMap::new();
+    pages.into_iter().for_each(|page| {
+        let key = if page.path().is_empty() {
+            page.name.clone()
+        } else {
+            page.path()
+        };
+        by_path.insert(key, page);
+    });
+    let mut pages: Vec<CmsFile> = by_path.into_values().collect();
+    pages.sort_by_key(|page| page.path());
+
+    logger::println(format!(
+        "backup of {} ({}) taken on {}",
+        manifest.environment,
+        manifest.server,
+        chrono::DateTime::from_timestamp_millis(manifest.timestamp)
+            .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default()
+    ));
+
+    if !confirm(format!(
+        "Restore {} page(s) and {} asset(s) to {} ?",
+        pages.len(),
+        manifest.assets.len(),
+        environment.name
+    ))? {
+        return Err(DaikokuCliError::Aborted("restore cancelled".to_string()));
+    }
+
+    logger::loading(format!(
+        "<yellow>Restoring</> {} page(s) to {}",
+        pages.len(),
+        environment.name
+    ));
+
+    if !pages.is_empty() {
+        sync_pages(&pages).await?;
+    }
+
+    let existing_assets = fetch_assets(&environment).await?;
+
+    for asset in &manifest.assets {
+        let content = read_entry(&mut archive, &asset_entry(&asset.slug))?;
+
+        upload_asset(
+            &environment,
+            &asset.slug,
+            &asset.meta,
+            Bytes::from(content),
+            asset
+                .content_type
+                .as_ref()
+                .and_then(|content_type| content_type.parse::<Mime>().ok()),
+            existing_assets
+                .iter()
+                .find(|existing| existing.slug.as_ref() == Some(&asset.slug)),
+        )
+        .await?;
+
+        logger::indent_println(asset.slug.clone());
+    }
+
+    // recorded in the history of the default project, when there is one
+    let snapshot = match cms::get_default_project() {
+        Ok(project) => {
+            let snapshot = PushSnapshot::new(&project.path, &environment.name, &pages);
+            snapshot.save(&project.path)?;
+            Some(snapshot.id)
+        }
+        Err(_) => None,
+    };
+
+    logger::success(format!("restore of {} done", archive_path));
+    logger::json(&serde_json::json!({
+        "environment": environment.name,
+        "archive": archive_path,
+        "pushed": pages.iter().map(|page| page.path()).collect::<Vec<String>>(),
+        "assets": manifest.assets.iter().map(|asset| &asset.slug).collect::<Vec<&String>>(),
+        "snapshot": snapshot,
+    }));
+
+    Ok(())
+}
@@ -3,23 +3,30 @@ use std::{
     fs::{self, create_dir, File},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use async_recursion::async_recursion;
 use configparser::ini::Ini;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
+use super::environments::{read_variable, Environment};
 use crate::{
     helpers::{
         bytes_to_struct, bytes_to_vec_of_struct, map_error_to_filesystem_error,
-        raw_daikoku_cms_api_get,
+        raw_daikoku_cms_api_get, raw_daikoku_cms_api_status,
     },
-    interactive::prompt,
+    interactive::confirm,
     logging::{
         error::{DaikokuCliError, DaikokuResult},
         logger,
     },
-    models::folder::{Ext, SourceExtension},
+    models::{
+        folder::{Ext, SourceExtension},
+        front_matter::{self, Metadata},
+        project_manifest::{ProjectManifest, SourceFolder},
+    },
     process,
     utils::{absolute_path, new_custom_ini_file},
     CmsCommands, Commands,
@@ -35,17 +42,19 @@ pub(crate) struct Project {
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub(crate) struct CmsPage {
     pub(crate) _id: String,
-    visible: bool,
-    authenticated: bool,
+    pub(crate) visible: bool,
+    pub(crate) authenticated: bool,
     pub(crate) name: String,
     #[serde(alias = "contentType")]
     pub(crate) content_type: String,
     pub(crate) path: Option<String>,
-    exact: bool,
+    pub(crate) exact: bool,
     #[serde(alias = "lastPublishedDate")]
-    last_published_date: Option<u64>,
+    pub(crate) last_published_date: Option<u64>,
     #[serde(alias = "body")]
     pub(crate) content: String,
+    #[serde(default)]
+    pub(crate) metadata: HashMap<String, String>,
 }
 
 #[derive(Clone, Deserialize, Serialize, Debug)]
@@ -94,6 +103,28 @@ pub(crate) struct Api {
 pub(crate) const EXCLUDE_API: [&'static str; 2] =
     ["admin-api-tenant-default", "cms-api-tenant-default"];
 
+const SOURCE_FOLDERS: [&str; 7] = [
+    "blocks",
+    "styles",
+    "scripts",
+    "data",
+    "mails",
+    "apis",
+    "documentations",
+];
+
+/// metadata computed by the CLI when reading a file, never written in front-matter
+const GENERATED_METADATA: [&str; 8] = [
+    "_path",
+    "_content_type",
+    "_name",
+    "from",
+    "_authenticated",
+    "_visible",
+    "_exact",
+    "_last_published_date",
+];
+
 pub(crate) async fn run(command: CmsCommands) -> DaikokuResult<()> {
     match command {
         CmsCommands::Add {
@@ -125,7 +156,52 @@ pub(crate) async fn run(command: CmsCommands) -> DaikokuResult<()> {
     }
 }
 
+/// name or path of the project to use instead of the default one
+pub(crate) const DAIKOKU_PROJECT: &str = "DAIKOKU_PROJECT";
+
+/// project given with the --project flag
+static PROJECT: OnceCell<String> = OnceCell::new();
+static PROJECT_PRINTED: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_project(project: Option<String>) {
+    if let Some(project) = project {
+        let _ = PROJECT.set(project);
+    }
+}
+
+/// Project used by the commands : the --project flag, the DAIKOKU_PROJECT variable, the nearest
+/// project containing the current directory, and finally the default project
 pub(crate) fn get_default_project() -> DaikokuResult<Project> {
+    let (project, origin) = if let Some(project) = PROJECT.get() {
+        (get_project_from_name_or_path(project.clone())?, "--project")
+    } else if let Some(project) = read_variable(DAIKOKU_PROJECT) {
+        (get_project_from_name_or_path(project)?, DAIKOKU_PROJECT)
+    } else if let Some(project) = find_project_from_current_dir() {
+        (project, "current directory")
+    } else {
+        (get_global_default_project()?, "default project")
+    };
+
+    if !PROJECT_PRINTED.swap(true, Ordering::SeqCst) {
+        logger::info(format!("<yellow>Project</> {} ({})", project.path, origin));
+    }
+
+    Ok(project)
+}
+
+/// walks up from the current directory to the nearest folder containing a .daikoku folder
+fn find_project_from_current_dir() -> Option<Project> {
+    let current_dir = std::env::current_dir().ok()?;
+
+    current_dir
+        .ancestors()
+        .find(|folder| folder.join(".daikoku").is_dir())
+        .map(|folder| Project {
+            path: folder.to_string_lossy().into_owned(),
+        })
+}
+
+fn get_global_default_project() -> DaikokuResult<Project> {
     let config = read(false)?;
 
     let default_project_name =
@@ -232,6 +308,19 @@ pub(crate) fn get_project(name: String) -> DaikokuResult<Project> {
     }
 }
 
+fn get_project_from_name_or_path(project: String) -> DaikokuResult<Project> {
+    if Path::new(&project).join(".daikoku").exists() {
+        return Ok(Project {
+            path: absolute_path(project)?,
+        });
+    }
+
+    internal_get_project(project.clone()).ok_or(DaikokuCliError::Configuration(format!(
+        "{} is neither a registered project nor the path of a project",
+        project
+    )))
+}
+
 fn internal_get_project(name: String) -> Option<Project> {
     let config = read(false).ok()?;
 
@@ -264,6 +353,7 @@ fn list() -> DaikokuResult<()> {
     let map = config.get_map().map(Ok).unwrap_or(Ok(HashMap::new()))?;
 
     logger::info(serde_json::to_string_pretty(&map).unwrap());
+    logger::json(&map);
 
     Ok(())
 }
@@ -351,14 +441,10 @@ fn clear(force: bool) -> DaikokuResult<()> {
         return remove_cms();
     }
 
-    logger::error("Are you to delete all cms ? [yN]".to_string());
-
-    let choice = prompt()?;
-
-    if choice.trim() == "y" {
+    if confirm("Are you to delete all cms ?".to_string())? {
         remove_cms()
     } else {
-        Ok(())
+        Err(DaikokuCliError::Aborted("clearing cancelled".to_string()))
     }
 }
 
@@ -446,21 +532,15 @@ async fn migrate(name: String, path: String, server: String, apikey: String) ->
         force_clearing_default_project()?
     }
 
-    if raw_daikoku_cms_api_get("/health", &server.clone(), &apikey)
-        .await?
-        .status
-        != 200
-    {
+    let environment = Environment::from_server(&server, Some(&apikey));
+
+    if raw_daikoku_cms_api_status("/health", &environment).await? != 200 {
         return Err(DaikokuCliError::Configuration(
             "Failed to join Daikoku server".to_string(),
         ));
     }
 
-    if raw_daikoku_cms_api_get("/version", &server, &apikey)
-        .await?
-        .status
-        != 404
-    {
+    if raw_daikoku_cms_api_status("/version", &environment).await? != 404 {
         return Err(DaikokuCliError::DaikokuStrError(
             "The CMS version in Daikoku is too recent to be migrated".to_string(),
         ));
@@ -470,44 +550,38 @@ async fn migrate(name: String, path: String, server: String, apikey: String) ->
         .map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?
         .join(name.clone());
 
-    let sources_path = project_path.join("src");
+    let project_manifest = ProjectManifest::load(&project_path)?;
 
     let root_mail_user_translations = bytes_to_struct::<IntlTranslationBody>(
         raw_daikoku_cms_api_get(
             "/translations/_mail?domain=tenant.mail.template",
-            &server,
-            &apikey,
+            &environment,
         )
-        .await?
-        .response,
+        .await?,
     )?;
 
     let mail_user_template = bytes_to_struct::<IntlTranslationBody>(
-        raw_daikoku_cms_api_get("/translations/_mail?domain=mail", &server, &apikey)
-            .await?
-            .response,
+        raw_daikoku_cms_api_get("/translations/_mail?domain=mail", &environment).await?,
     )?;
 
     let apis_informations: Vec<Api> = bytes_to_vec_of_struct::<Api>(
         raw_daikoku_cms_api_get(
             "/apis?fields=_id,_humanReadableId,header,description",
-            &server,
-            &apikey,
+            &environment,
         )
-        .await?
-        .response,
+        .await?,
     )?
     .into_iter()
     .filter(|api| !EXCLUDE_API.contains(&api._id.as_str()))
     .collect();
 
-    create_mail_folder(root_mail_user_translations, sources_path.clone(), true)?;
-    create_mail_folder(mail_user_template, sources_path.clone(), false)?;
+    create_mail_folder(root_mail_user_translations, &project_manifest, true)?;
+    create_mail_folder(mail_user_template, &project_manifest, false)?;
 
-    create_api_folder(apis_informations, sources_path.clone())?;
+    create_api_folder(apis_informations, &project_manifest)?;
 
     logger::info("create_cms_pages".to_string());
-    create_cms_pages(&sources_path, &server, &apikey).await?;
+    create_cms_pages(&project_manifest, &environment).await?;
 
     logger::info("create_daikoku_hidden_files".to_string());
     create_daikoku_hidden_files(project_path.clone())?;
@@ -558,16 +632,14 @@ fn replace_ids(items: Vec<CmsPage>) -> DaikokuResult<Vec<CmsPage>> {
 
 pub(crate) fn create_api_folder(
     apis: Vec<Api>,
-    project_path: PathBuf,
+    project_manifest: &ProjectManifest,
 ) -> DaikokuResult<Vec<String>> {
     let mut created: Vec<String> = Vec::new();
 
     apis.iter().for_each(|item| {
-        let file_path = project_path.clone().join(
-            PathBuf::from_str("apis")
-                .unwrap()
-                .join(item.human_readable_id.clone()),
-        );
+        let file_path = project_manifest
+            .folder(SourceFolder::Apis)
+            .join(item.human_readable_id.clone());
 
         if !file_path.exists() {
             let _ = fs::create_dir_all(file_path.clone());
@@ -611,13 +683,15 @@ pub(crate) fn create_api_folder(
 
 pub(crate) fn create_mail_folder(
     intl_translation: IntlTranslationBody,
-    project_path: PathBuf,
+    project_manifest: &ProjectManifest,
     is_root_mail: bool,
-) -> DaikokuResult<()> {
+) -> DaikokuResult<Vec<String>> {
+    let mut created: Vec<String> = Vec::new();
+
     intl_translation.translations.iter().for_each(|item| {
-        let mail_folder = project_path
-            .clone()
-            .join(get_mail_page_path(&item._id.clone().replace(".", "-"), is_root_mail).unwrap());
+        let mail_folder = project_manifest.local_path(
+            &get_mail_page_path(&item._id.clone().replace(".", "-"), is_root_mail).unwrap(),
+        );
 
         let mut config: Ini = new_custom_ini_file();
 
@@ -631,44 +705,46 @@ pub(crate) fn create_mail_folder(
                         .join(translation.language.clone());
 
             if !file_path.exists() {
-                let _ = create_path_and_file(
-                    mail_folder
-                        .clone()
-                        .join(translation.language.clone())
-                        .join("page.html"),
+                let page_path = mail_folder
+                    .clone()
+                    .join(translation.language.clone())
+                    .join("page.html");
+
+                if create_path_and_file(
+                    page_path.clone(),
                     translation.value.clone().replace("''", "'"),
                     translation._id.clone(),
                     HashMap::new(),
                     SourceExtension::HTML,
-                );
+                )
+                .is_ok()
+                {
+                    created.push(page_path.to_string_lossy().into_owned());
+                }
             }
         })
     });
 
-    Ok(())
+    Ok(created)
 }
 
 async fn create_cms_pages(
-    sources_path: &PathBuf,
-    server: &String,
-    apikey: &String,
+    project_manifest: &ProjectManifest,
+    environment: &Environment,
 ) -> DaikokuResult<()> {
-    let items = bytes_to_vec_of_struct::<CmsPage>(
-        raw_daikoku_cms_api_get("/pages", &server, &apikey)
-            .await?
-            .response,
-    )?;
+    let items =
+        bytes_to_vec_of_struct::<CmsPage>(raw_daikoku_cms_api_get("/pages", environment).await?)?;
 
     let new_pages = replace_ids(items)?;
 
-    convert_cms_pages(new_pages, sources_path.clone())
+    convert_cms_pages(new_pages, project_manifest)
 }
 
-fn convert_cms_pages(items: Vec<CmsPage>, project_path: PathBuf) -> DaikokuResult<()> {
+fn convert_cms_pages(items: Vec<CmsPage>, project_manifest: &ProjectManifest) -> DaikokuResult<()> {
     items.iter().for_each(|item| {
-        let extension = SourceExtension::from_str(&item.content_type).unwrap();
+        let extension = SourceExtension::from_content_type(&item.content_type);
 
-        let file_path = project_path.clone().join(get_cms_page_path(item).unwrap());
+        let file_path = project_manifest.local_path(&get_cms_page_path(item).unwrap());
 
         let metadata = extract_metadata(item).unwrap_or(HashMap::new());
 
@@ -693,7 +769,7 @@ fn get_mail_page_path(filename: &String, is_root_mail: bool) -> DaikokuResult<Pa
 }
 
 fn get_cms_page_path(item: &CmsPage) -> DaikokuResult<PathBuf> {
-    let extension = SourceExtension::from_str(&item.content_type).unwrap();
+    let extension = SourceExtension::from_content_type(&item.content_type);
 
     let mut folder = match extension {
         SourceExtension::HTML => item.path.clone().map(|_| "pages").unwrap_or("blocks"),
@@ -736,12 +812,6 @@ fn get_cms_page_path(item: &CmsPage) -> DaikokuResult<PathBuf> {
         ))
     };
 
-    let parent = folder_path.parent().unwrap();
-
-    if !parent.exists() {
-        fs::create_dir_all(parent).map_err(|err| map_error_to_filesystem_error(err, ""))?;
-    }
-
     Ok(folder_path)
 }
 
@@ -749,7 +819,7 @@ pub fn create_path_and_file(
     file_buf: PathBuf,
     content: String,
     name: String,
-    metadata: HashMap<String, String>,
+    metadata: Metadata,
     content_type: SourceExtension,
 ) -> DaikokuResult<()> {
     let parent =
@@ -780,33 +850,102 @@ pub fn create_path_and_file(
 
     logger::println(format!("Creating {} {:?}", name, file_path));
 
-    if content_type == SourceExtension::HTML {
-        if metadata.is_empty() {
-            Ok(fs::write(file_path, content)
-                .map_err(|err| map_error_to_filesystem_error(err, ""))?)
-        } else {
-            let metadata_header = serde_yaml::to_string(&metadata).map_err(|_err| {
-                DaikokuCliError::ParsingError(format!("failed parsing metadata {}", &name))
-            })?;
-
-            Ok(
-                fs::write(file_path, format!("{}\n---\n{}", metadata_header, content))
-                    .map_err(|err| map_error_to_filesystem_error(err, ""))?,
-            )
-        }
+    let content = format_file_content(content, &name, &metadata, &content_type)?;
+
+    fs::write(file_path, content).map_err(|err| map_error_to_filesystem_error(err, ""))
+}
+
+/// Content of a source file as written on disk : HTML pages carry their metadata as front-matter
+pub(crate) fn format_file_content(
+    content: String,
+    name: &String,
+    metadata: &Metadata,
+    content_type: &SourceExtension,
+) -> DaikokuResult<String> {
+    if *content_type == SourceExtension::HTML && !metadata.is_empty() {
+        front_matter::format(metadata, &content).map_err(|_err| {
+            DaikokuCliError::ParsingError(format!("failed parsing metadata {}", name))
+        })
+    } else {
+        Ok(content)
+    }
+}
+
+/// Location of a page pushed by the CLI, rebuilt from the `_path` and `_name` metadata computed
+/// when the project was read
+fn get_cli_page_path(item: &CmsPage) -> Option<PathBuf> {
+    if item.metadata.get("from").map(|from| from.as_str()) != Some("cli") {
+        return None;
+    }
+
+    let route = PathBuf::from(item.path.clone()?.trim_start_matches("/"));
+    let name = item
+        .metadata
+        .get("_name")
+        .cloned()
+        .unwrap_or(item.name.clone());
+
+    let relative_path = if name == "page.html" {
+        route.join(name)
+    } else {
+        route
+            .parent()
+            .map(|parent| parent.join(&name))
+            .unwrap_or(PathBuf::from(&name))
+    };
+
+    let in_source_folder = relative_path
+        .iter()
+        .next()
+        .and_then(|folder| folder.to_str())
+        .map(|folder| SOURCE_FOLDERS.contains(&folder))
+        .unwrap_or(false);
+
+    if in_source_folder && relative_path.iter().count() > 1 {
+        Some(relative_path)
     } else {
-        Ok(fs::write(file_path, content).map_err(|err| map_error_to_filesystem_error(err, ""))?)
+        Some(PathBuf::from("pages").join(relative_path))
     }
 }
 
-fn extract_metadata(item: &CmsPage) -> DaikokuResult<HashMap<String, String>> {
-    let mut metadata: HashMap<String, String> = HashMap::new();
+/// Location of a remote page in the CMS layout, see `ProjectManifest::local_path` for its location
+/// on disk
+pub(crate) fn get_local_page_path(item: &CmsPage) -> DaikokuResult<PathBuf> {
+    match get_cli_page_path(item) {
+        Some(path) => Ok(path),
+        None => get_cms_page_path(item),
+    }
+}
+
+/// Front-matter written for a remote page : metadata set by the user and the page flags
+pub(crate) fn get_page_file_metadata(item: &CmsPage) -> Metadata {
+    let path = item.path.clone().unwrap_or_default();
+
+    if path.starts_with("/apis/") || path.starts_with("/mails/") {
+        return HashMap::new();
+    }
+
+    let mut metadata: Metadata = item
+        .metadata
+        .iter()
+        .filter(|(key, _)| !GENERATED_METADATA.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone().into()))
+        .collect();
+
+    metadata.extend(extract_metadata(item).unwrap_or_default());
+
+    metadata
+}
+
+fn extract_metadata(item: &CmsPage) -> DaikokuResult<Metadata> {
+    let mut metadata: Metadata = HashMap::new();
 
-    metadata.insert("_authenticated".to_string(), item.authenticated.to_string());
-    metadata.insert("_visible".to_string(), item.visible.to_string());
-    metadata.insert("_exact".to_string(), item.exact.to_string());
+    metadata.insert("id".to_string(), item._id.clone().into());
+    metadata.insert("_authenticated".to_string(), item.authenticated.to_string().into());
+    metadata.insert("_visible".to_string(), item.visible.to_string().into());
+    metadata.insert("_exact".to_string(), item.exact.to_string().into());
     item.last_published_date
-        .map(|value| metadata.insert("_last_published_date".to_string(), value.to_string()));
+        .map(|value| metadata.insert("_last_published_date".to_string(), value.to_string().into()));
     Ok(metadata)
 }
 
@@ -843,6 +982,11 @@ async fn create_environment(name: String, server: String, apikey: String) -> Dai
             server,
             apikey,
             overwrite: Some(true),
+            ca_file: None,
+            client_cert: None,
+            client_key: None,
+            insecure: false,
+            proxy: None,
         },
     })
     .await?;
@@ -0,0 +1,254 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use similar::TextDiff;
+
+use crate::{
+    helpers::{bytes_to_vec_of_struct, daikoku_cms_api_get},
+    logging::{error::DaikokuResult, logger},
+    models::{
+        folder::{read_sources_and_daikoku_metadata, CmsFile},
+        project_manifest::ProjectManifest,
+    },
+};
+
+use super::cms::{self, CmsPage};
+
+#[derive(Debug)]
+pub(crate) enum PageChange {
+    Added(CmsFile),
+    Modified {
+        local: CmsFile,
+        remote: CmsPage,
+        metadata: Vec<MetadataChange>,
+    },
+    Deleted(CmsPage),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct MetadataChange {
+    key: String,
+    remote: String,
+    local: String,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct ChangeSummary {
+    status: String,
+    path: String,
+    metadata: Vec<MetadataChange>,
+    diff: Option<String>,
+}
+
+pub(crate) async fn run() -> DaikokuResult<()> {
+    logger::loading("<yellow>Comparing</> local project with Daikoku".to_string());
+
+    let project = cms::get_default_project()?;
+
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
+
+    let local_pages = read_sources_and_daikoku_metadata(&project_manifest).check(false)?;
+
+    let remote_pages = fetch_remote_pages().await?;
+
+    logger::done();
+
+    let changes = compute_changes(&local_pages, &remote_pages, true);
+
+    print_changes(&changes);
+    logger::json(&summarize_changes(&changes));
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_remote_pages() -> DaikokuResult<Vec<CmsPage>> {
+    bytes_to_vec_of_struct::<CmsPage>(daikoku_cms_api_get("/pages").await?)
+}
+
+/// a page is found by its id first, so that a renamed file still matches its remote page
+fn find_remote_page<'a>(file: &CmsFile, remote_pages: &'a [CmsPage]) -> Option<&'a CmsPage> {
+    let path = file.path();
+
+    file.id()
+        .and_then(|id| remote_pages.iter().find(|page| page._id == id))
+        .or_else(|| {
+            remote_pages
+                .iter()
+                .find(|page| page.path.as_ref() == Some(&path))
+        })
+        .or_else(|| {
+            remote_pages
+                .iter()
+                .find(|page| page.path.is_none() && page.name == file.name)
+        })
+}
+
+fn local_bool(file: &CmsFile, key: &str, default: bool) -> bool {
+    file.metadata_text(key)
+        .map(|value| value.parse().unwrap_or(default))
+        .unwrap_or(default)
+}
+
+fn metadata_changes(local: &CmsFile, remote: &CmsPage) -> Vec<MetadataChange> {
+    [
+        ("_authenticated", remote.authenticated, false),
+        ("_visible", remote.visible, true),
+        ("_exact", remote.exact, false),
+    ]
+    .into_iter()
+    .filter_map(|(key, remote_value, default)| {
+        let local_value = local_bool(local, key, default);
+
+        if local_value != remote_value {
+            Some(MetadataChange {
+                key: key.to_string(),
+                remote: remote_value.to_string(),
+                local: local_value.to_string(),
+            })
+        } else {
+            None
+        }
+    })
+    .collect()
+}
+
+/// Matches local files against remote pages by path (or by name for pages without path) and
+/// returns what a push would change on Daikoku
+pub(crate) fn compute_changes(
+    local_pages: &[CmsFile],
+    remote_pages: &[CmsPage],
+    include_deleted: bool,
+) -> Vec<PageChange> {
+    let mut changes: Vec<PageChange> = Vec::new();
+    let mut matched: Vec<String> = Vec::new();
+
+    for file in local_pages {
+        match find_remote_page(file, remote_pages) {
+            None => changes.push(PageChange::Added(file.clone())),
+            Some(remote) => {
+                matched.push(remote._id.clone());
+
+                let metadata = metadata_changes(file, remote);
+
+                if remote.content != file.content || !metadata.is_empty() {
+                    changes.push(PageChange::Modified {
+                        local: file.clone(),
+                        remote: remote.clone(),
+                        metadata,
+                    })
+                }
+            }
+        }
+    }
+
+    if include_deleted {
+        remote_pages
+            .iter()
+            .filter(|page| !matched.contains(&page._id))
+            .for_each(|page| changes.push(PageChange::Deleted(page.clone())));
+    }
+
+    changes
+}
+
+pub(crate) fn remote_page_label(page: &CmsPage) -> String {
+    page.path.clone().unwrap_or(page.name.clone())
+}
+
+fn unified_diff(local: &CmsFile, remote: &CmsPage) -> Option<String> {
+    if remote.content == local.content {
+        return None;
+    }
+
+    Some(
+        TextDiff::from_lines(&remote.content, &local.content)
+            .unified_diff()
+            .context_radius(3)
+            .header(
+                &format!("remote{}", local.path()),
+                &format!("local{}", local.path()),
+            )
+            .to_string(),
+    )
+}
+
+pub(crate) fn summarize_changes(changes: &[PageChange]) -> Vec<ChangeSummary> {
+    changes
+        .iter()
+        .map(|change| match change {
+            PageChange::Added(file) => ChangeSummary {
+                status: "added".to_string(),
+                path: file.path(),
+                metadata: vec![],
+                diff: None,
+            },
+            PageChange::Modified {
+                local,
+                remote,
+                metadata,
+            } => ChangeSummary {
+                status: "modified".to_string(),
+                path: local.path(),
+                metadata: metadata.clone(),
+                diff: unified_diff(local, remote),
+            },
+            PageChange::Deleted(page) => ChangeSummary {
+                status: "deleted".to_string(),
+                path: remote_page_label(page),
+                metadata: vec![],
+                diff: None,
+            },
+        })
+        .collect()
+}
+
+/// human readable version of the changes, the json output uses summarize_changes
+pub(crate) fn print_changes(changes: &[PageChange]) {
+    if logger::is_json() {
+        return;
+    }
+
+    if changes.is_empty() {
+        logger::println("nothing to push, remote pages are up to date".to_string());
+        return;
+    }
+
+    let (mut added, mut modified, mut deleted) = (0, 0, 0);
+
+    for change in changes {
+        match change {
+            PageChange::Added(file) => {
+                added += 1;
+                logger::println(format!("<green>+ added</> {}", file.path()));
+            }
+            PageChange::Modified {
+                local,
+                remote,
+                metadata,
+            } => {
+                modified += 1;
+                logger::println(format!("<yellow>~ modified</> {}", local.path()));
+
+                metadata.iter().for_each(|change| {
+                    logger::indent_println(format!(
+                        "{} : {} -> {}",
+                        change.key, change.remote, change.local
+                    ))
+                });
+
+                if let Some(diff) = unified_diff(local, remote) {
+                    println!("{}", diff);
+                }
+            }
+            PageChange::Deleted(page) => {
+                deleted += 1;
+                logger::println(format!("<red>- deleted</> {}", remote_page_label(page)));
+            }
+        }
+    }
+
+    logger::info(format!(
+        "{} added, {} modified, {} deleted",
+        added, modified, deleted
+    ));
+}
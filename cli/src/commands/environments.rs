@@ -1,10 +1,11 @@
 use crate::{
-    helpers::{daikoku_cms_api_get, raw_daikoku_cms_api_get},
-    interactive::prompt,
+    helpers::raw_daikoku_cms_api_status,
+    interactive::confirm,
     logging::{
         error::{DaikokuCliError, DaikokuResult},
         logger,
     },
+    models::project_manifest::ProjectManifest,
     utils::{apply_credentials_mask, new_custom_ini_file},
     EnvironmentsCommands,
 };
@@ -12,18 +13,67 @@ use configparser::ini::Ini;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    fs,
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
 use super::cms;
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub(crate) struct Environment {
     pub(crate) server: String,
     pub(crate) cookie: Option<String>,
     pub(crate) apikey: Option<String>,
     pub(crate) name: String,
+    /// seconds before a request to Daikoku is abandoned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout: Option<String>,
+    /// seconds before the connection to Daikoku is abandoned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) connect_timeout: Option<String>,
+    /// attempts after a request failed on a transient error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) retries: Option<String>,
+    /// PEM bundle of the authorities trusted in addition to the system ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) ca_file: Option<String>,
+    /// PEM certificate presented to the servers requiring mutual TLS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) client_cert: Option<String>,
+    /// PEM (PKCS #8) key of the client certificate, stored in the .secrets file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) client_key: Option<String>,
+    /// whether invalid certificates are accepted, for local development only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) insecure: Option<String>,
+    /// URL of the proxy used to reach Daikoku, none to ignore the proxy variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) proxy: Option<String>,
+}
+
+impl Environment {
+    /// environment of a server which isn't saved in the .environments file yet
+    pub(crate) fn from_server(server: &str, apikey: Option<&String>) -> Environment {
+        Environment {
+            server: server.to_string(),
+            apikey: apikey.cloned(),
+            ..Environment::default()
+        }
+    }
+}
+
+/// variables taking precedence over the .environments and .secrets files, useful to run the CLI
+/// in CI jobs without writing secrets to disk
+pub(crate) const DAIKOKU_SERVER: &str = "DAIKOKU_SERVER";
+pub(crate) const DAIKOKU_APIKEY: &str = "DAIKOKU_APIKEY";
+pub(crate) const DAIKOKU_COOKIE: &str = "DAIKOKU_COOKIE";
+pub(crate) const DAIKOKU_ENVIRONMENT: &str = "DAIKOKU_ENVIRONMENT";
+
+/// name of the environment defined by the DAIKOKU_SERVER variable when DAIKOKU_ENVIRONMENT is missing
+const VARIABLES_ENVIRONMENT_NAME: &str = "env";
+
+pub(crate) fn read_variable(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
 }
 
 pub(crate) async fn run(command: EnvironmentsCommands) -> DaikokuResult<()> {
@@ -34,7 +84,27 @@ pub(crate) async fn run(command: EnvironmentsCommands) -> DaikokuResult<()> {
             server,
             overwrite,
             apikey,
-        } => add(name, server, overwrite.unwrap_or(false), apikey).await,
+            ca_file,
+            client_cert,
+            client_key,
+            insecure,
+            proxy,
+        } => {
+            add(
+                name,
+                server,
+                overwrite.unwrap_or(false),
+                apikey,
+                ConnectionSettings {
+                    ca_file,
+                    client_cert,
+                    client_key,
+                    insecure,
+                    proxy,
+                },
+            )
+            .await
+        }
         EnvironmentsCommands::Switch { name } => switch_environment(name),
         EnvironmentsCommands::Remove { name } => remove(name),
         EnvironmentsCommands::Info { name, full } => info(name, full.unwrap_or(false)),
@@ -111,40 +181,37 @@ fn clear(force: bool) -> DaikokuResult<()> {
         return clearing();
     }
 
-    logger::error("Are you to delete all environments ? [yN]".to_string());
-
-    let choice = prompt()?;
-
-    if choice.trim() == "y" {
+    if confirm("Are you to delete all environments ?".to_string())? {
         clearing()
     } else {
-        Ok(())
+        Err(DaikokuCliError::Aborted("clearing cancelled".to_string()))
     }
 }
 
-pub(crate) async fn can_join_daikoku(
-    server: &String,
-    apikey: Option<&String>,
-) -> DaikokuResult<bool> {
-    let host = server.replace("http://", "").replace("https://", "");
-
-    let url: String = format!("{}/health", host);
-
-    let status = match apikey {
-        None => daikoku_cms_api_get(&url).await?.status,
-        Some(apikey) => {
-            raw_daikoku_cms_api_get("/health", &server.clone(), &apikey)
-                .await?
-                .status
-        }
-    };
+pub(crate) async fn can_join_daikoku(environment: &Environment) -> DaikokuResult<bool> {
+    let status = raw_daikoku_cms_api_status("/health", environment).await?;
 
     logger::println(format!("Daikoku have returned : {}", status));
 
     Ok(status == 200)
 }
 
-async fn add(name: String, server: String, overwrite: bool, apikey: String) -> DaikokuResult<()> {
+/// TLS and proxy settings of the add command
+pub(crate) struct ConnectionSettings {
+    pub(crate) ca_file: Option<String>,
+    pub(crate) client_cert: Option<String>,
+    pub(crate) client_key: Option<String>,
+    pub(crate) insecure: bool,
+    pub(crate) proxy: Option<String>,
+}
+
+async fn add(
+    name: String,
+    server: String,
+    overwrite: bool,
+    apikey: String,
+    connection: ConnectionSettings,
+) -> DaikokuResult<()> {
     logger::loading("<yellow>Patching</> configuration".to_string());
     let mut config: Ini = read_environments()?;
 
@@ -174,7 +241,18 @@ async fn add(name: String, server: String, overwrite: bool, apikey: String) -> D
         apikey
     };
 
-    if !can_join_daikoku(&formatted_server, Some(&formatted_apikey)).await? {
+    let insecure = connection.insecure.then(|| "true".to_string());
+
+    if !can_join_daikoku(&Environment {
+        ca_file: connection.ca_file.clone(),
+        client_cert: connection.client_cert.clone(),
+        client_key: connection.client_key.clone(),
+        insecure: insecure.clone(),
+        proxy: connection.proxy.clone(),
+        ..Environment::from_server(&formatted_server, Some(&formatted_apikey))
+    })
+    .await?
+    {
         return Err(DaikokuCliError::Configuration(
             "failed to save configuration. The specified Daikoku server can not be reached"
                 .to_string(),
@@ -184,9 +262,14 @@ async fn add(name: String, server: String, overwrite: bool, apikey: String) -> D
     config.set(&name, "server", Some(formatted_server));
     config.set("default", "environment", Some(name.clone()));
     config.set(name.clone().as_str(), "name", Some(name.clone()));
+    config.set(&name, "ca_file", connection.ca_file);
+    config.set(&name, "client_cert", connection.client_cert);
+    config.set(&name, "insecure", insecure);
+    config.set(&name, "proxy", connection.proxy);
 
     let mut secrets: Ini = read_secrets()?;
     secrets.set(name.clone().as_str(), "apikey", Some(formatted_apikey));
+    secrets.set(&name, "client_key", connection.client_key);
 
     secrets
         .write(&get_secrets_path()?)
@@ -237,7 +320,12 @@ async fn configure(apikey: Option<String>, cookie: Option<String>) -> DaikokuRes
     if let Some(new_apikey) = apikey {
         config.set(&environment.name, "apikey", Some(new_apikey.clone()));
 
-        if !can_join_daikoku(&environment.server, Some(&new_apikey)).await? {
+        if !can_join_daikoku(&Environment {
+            apikey: Some(new_apikey.clone()),
+            ..environment.clone()
+        })
+        .await?
+        {
             return Err(DaikokuCliError::Configuration(
                 "failed to save configuration. The specified Daikoku server can not be reached"
                     .to_string(),
@@ -267,9 +355,22 @@ async fn configure(apikey: Option<String>, cookie: Option<String>) -> DaikokuRes
 }
 
 pub(crate) fn read_cookie_from_environment(failed_if_not_present: bool) -> DaikokuResult<String> {
-    let config: Ini = read_environments()?;
+    if let Some(cookie) = read_variable(DAIKOKU_COOKIE) {
+        return Ok(cookie);
+    }
 
-    if let Some(environment) = config.get("default", "environment") {
+    if read_variable(DAIKOKU_SERVER).is_some() {
+        return if failed_if_not_present {
+            Err(DaikokuCliError::Configuration(format!(
+                "Missing cookie. Set the {} variable",
+                DAIKOKU_COOKIE
+            )))
+        } else {
+            Ok("".to_string())
+        };
+    }
+
+    if let Some(environment) = default_environment_name()? {
         let secrets: Ini = read_secrets()?;
         secrets
             .get(&environment, "cookie")
@@ -289,9 +390,22 @@ pub(crate) fn read_cookie_from_environment(failed_if_not_present: bool) -> Daiko
 }
 
 pub(crate) fn read_apikey_from_secrets(failed_if_not_present: bool) -> DaikokuResult<String> {
-    let config: Ini = read_environments()?;
+    if let Some(apikey) = read_variable(DAIKOKU_APIKEY) {
+        return Ok(apikey.replace("Basic ", ""));
+    }
 
-    if let Some(environment) = config.get("default", "environment") {
+    if read_variable(DAIKOKU_SERVER).is_some() {
+        return if failed_if_not_present {
+            Err(DaikokuCliError::Configuration(format!(
+                "Missing apikey. Set the {} variable",
+                DAIKOKU_APIKEY
+            )))
+        } else {
+            Ok("".to_string())
+        };
+    }
+
+    if let Some(environment) = default_environment_name()? {
         let secrets: Ini = read_secrets()?;
         secrets
             .get(&environment, "apikey")
@@ -323,6 +437,7 @@ fn info(name: String, show_full_credentials: bool) -> DaikokuResult<()> {
         .map(|credential| apply_credentials_mask(&credential, show_full_credentials));
 
     logger::info(serde_json::to_string_pretty(&environment).unwrap());
+    logger::json(&environment);
     Ok(())
 }
 
@@ -371,6 +486,12 @@ fn get(name: String) -> DaikokuResult<Environment> {
 
     match values.get(&name) {
         Some(value) => serde_json::from_str(serde_json::to_string(&value).unwrap().as_str())
+            .map(|environment: Environment| Environment {
+                client_key: read_secrets()
+                    .ok()
+                    .and_then(|secrets| secrets.get(&name, "client_key")),
+                ..environment
+            })
             .map(Ok)
             .unwrap_or(Err(DaikokuCliError::Configuration(
                 "failed reading environment".to_string(),
@@ -381,10 +502,72 @@ fn get(name: String) -> DaikokuResult<Environment> {
     }
 }
 
+/// environment of the .environments file with its apikey, to call another environment than the
+/// default one
+pub(crate) fn get_environment_with_apikey(name: &str) -> DaikokuResult<Environment> {
+    let mut environment = get(name.to_string()).map_err(|_| {
+        DaikokuCliError::Configuration(format!(
+            "environment {} not found, see daikoku environments list",
+            name
+        ))
+    })?;
+
+    environment.apikey = Some(read_secrets()?.get(name, "apikey").ok_or_else(|| {
+        DaikokuCliError::Configuration(format!(
+            "Missing apikey on the {} environment. Run daikoku environments add with its apikey",
+            name
+        ))
+    })?);
+
+    Ok(environment)
+}
+
+/// DAIKOKU_ENVIRONMENT, then the default environment of the daikoku.toml file, then the one of
+/// the .environments file
+fn default_environment_name() -> DaikokuResult<Option<String>> {
+    if let Some(name) = read_variable(DAIKOKU_ENVIRONMENT) {
+        return Ok(Some(name));
+    }
+
+    let project = cms::get_default_project()?;
+
+    match ProjectManifest::load(&PathBuf::from(&project.path))?.default_environment {
+        Some(name) => Ok(Some(name)),
+        None => Ok(read_environments()?.get("default", "environment")),
+    }
+}
+
+static ENVIRONMENT_PRINTED: AtomicBool = AtomicBool::new(false);
+
+/// prints the environment the command acts on, once
+fn print_environment(environment: Environment) -> Environment {
+    if !ENVIRONMENT_PRINTED.swap(true, Ordering::SeqCst) {
+        logger::info(format!(
+            "<yellow>Environment</> {} ({})",
+            environment.name, environment.server
+        ));
+    }
+
+    environment
+}
+
 pub(crate) fn get_default_environment() -> DaikokuResult<Environment> {
-    let config: Ini = read_environments()?;
+    get_environment_from_variables_or_default().map(print_environment)
+}
+
+fn get_environment_from_variables_or_default() -> DaikokuResult<Environment> {
+    if let Some(server) = read_variable(DAIKOKU_SERVER) {
+        return Ok(Environment {
+            server: server.trim_end_matches("/").to_string(),
+            cookie: read_variable(DAIKOKU_COOKIE),
+            apikey: read_variable(DAIKOKU_APIKEY),
+            name: read_variable(DAIKOKU_ENVIRONMENT)
+                .unwrap_or(VARIABLES_ENVIRONMENT_NAME.to_string()),
+            ..Environment::default()
+        });
+    }
 
-    let default_environment = config.get("default", "environment").map(Ok).unwrap_or(Err(
+    let default_environment = default_environment_name()?.map(Ok).unwrap_or(Err(
         DaikokuCliError::Configuration(
             "default environment not found. see daikoku environments help".to_string(),
         ),
@@ -394,8 +577,8 @@ pub(crate) fn get_default_environment() -> DaikokuResult<Environment> {
 }
 
 pub(crate) fn check_environment_from_str(name: Option<String>) -> DaikokuResult<Environment> {
-    name.map(|project_name| get(project_name))
-        .unwrap_or(get_default_environment())
+    name.map(|project_name| get(project_name).map(print_environment))
+        .unwrap_or_else(get_default_environment)
 }
 
 fn list() -> DaikokuResult<()> {
@@ -404,29 +587,7 @@ fn list() -> DaikokuResult<()> {
     let map = config.get_map().unwrap_or(HashMap::new());
 
     logger::info(serde_json::to_string_pretty(&map).unwrap());
+    logger::json(&map);
 
     Ok(())
 }
-
-pub(crate) fn get_daikokuignore() -> DaikokuResult<Vec<String>> {
-    let project = cms::get_default_project()?;
-
-    let daikokuignore_path = Path::new(&PathBuf::from(project.path))
-        .join(".daikoku")
-        .join(".daikokuignore")
-        .into_os_string()
-        .into_string();
-
-    match daikokuignore_path {
-        Ok(path) => {
-            let content = fs::read_to_string(path)
-                .map_err(|err| DaikokuCliError::Configuration(err.to_string()))?;
-
-            Ok(content
-                .split("\n")
-                .map(|str| str.to_string())
-                .collect::<Vec<String>>())
-        }
-        Err(_) => Ok(Vec::new()),
-    }
-}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
+use std::{collections::HashMap, path::PathBuf};
 
 use uuid::Uuid;
 
@@ -8,7 +8,10 @@ use crate::{
         error::{DaikokuCliError, DaikokuResult},
         logger,
     },
-    models::folder::SourceExtension,
+    models::{
+        folder::SourceExtension,
+        project_manifest::{ProjectManifest, SourceFolder},
+    },
     GenerateCommands,
 };
 
@@ -51,10 +54,8 @@ async fn generate_documentation_page(
 
     logger::done();
 
-    let documentations_path = PathBuf::from_str(&project.path)
-        .unwrap()
-        .join("src")
-        .join("documentations");
+    let documentations_path = ProjectManifest::load(&PathBuf::from(&project.path))?
+        .folder(SourceFolder::Documentations);
 
     let page_path = documentations_path
         .clone()
@@ -67,9 +68,9 @@ async fn generate_documentation_page(
     }
 
     let mut metadata = HashMap::new();
-    metadata.insert("title".to_string(), title);
-    metadata.insert("description".to_string(), desc);
-    metadata.insert("id".to_string(), Uuid::new_v4().to_string());
+    metadata.insert("title".to_string(), title.into());
+    metadata.insert("description".to_string(), desc.into());
+    metadata.insert("id".to_string(), Uuid::new_v4().to_string().into());
 
     create_path_and_file(
         page_path,
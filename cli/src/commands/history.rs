@@ -0,0 +1,132 @@
+use serde::Serialize;
+
+use crate::{
+    interactive::confirm,
+    logging::{
+        error::{DaikokuCliError, DaikokuResult},
+        logger,
+    },
+    models::{history::PushSnapshot, manifest::PushManifest},
+};
+
+use super::{cms, environments::get_default_environment, push::sync_pages};
+
+#[derive(Serialize, Debug)]
+struct SnapshotSummary {
+    id: String,
+    environment: String,
+    timestamp: i64,
+    commit: Option<String>,
+    pages: usize,
+    pruned: usize,
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(timestamp)
+        .map(|date| {
+            date.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) async fn list() -> DaikokuResult<()> {
+    let project = cms::get_default_project()?;
+
+    let mut snapshots = PushSnapshot::list(&project.path)?;
+    snapshots.reverse();
+
+    if snapshots.is_empty() {
+        logger::println("no push recorded yet".to_string());
+    }
+
+    snapshots.iter().for_each(|snapshot| {
+        logger::println(format!(
+            "<yellow>{}</> {} {} page(s){} to {}{}",
+            snapshot.id,
+            format_timestamp(snapshot.timestamp),
+            snapshot.pages.len(),
+            if snapshot.pruned.is_empty() {
+                "".to_string()
+            } else {
+                format!(", {} pruned", snapshot.pruned.len())
+            },
+            snapshot.environment,
+            snapshot
+                .commit
+                .as_ref()
+                .map(|commit| format!(" at {}", &commit[..commit.len().min(8)]))
+                .unwrap_or_default()
+        ))
+    });
+
+    logger::json(
+        &snapshots
+            .into_iter()
+            .map(|snapshot| SnapshotSummary {
+                pages: snapshot.pages.len(),
+                pruned: snapshot.pruned.len(),
+                id: snapshot.id,
+                environment: snapshot.environment,
+                timestamp: snapshot.timestamp,
+                commit: snapshot.commit,
+            })
+            .collect::<Vec<SnapshotSummary>>(),
+    );
+
+    Ok(())
+}
+
+/// Pushes again the pages of the environment as they were after the push of the snapshot
+pub(crate) async fn rollback(id: String) -> DaikokuResult<()> {
+    let project = cms::get_default_project()?;
+
+    let snapshot = PushSnapshot::load(&project.path, &id)?;
+
+    let environment = get_default_environment()?;
+
+    if environment.name != snapshot.environment {
+        return Err(DaikokuCliError::Configuration(format!(
+            "snapshot {} was pushed to the {} environment, switch to it with daikoku environments switch or DAIKOKU_ENVIRONMENT",
+            snapshot.id, snapshot.environment
+        )));
+    }
+
+    let pages = snapshot.pages_at(&project.path)?;
+
+    pages
+        .iter()
+        .for_each(|page| logger::indent_println(page.path()));
+
+    if !confirm(format!(
+        "Push these {} page(s) to {} as they were after the push {} ?",
+        pages.len(),
+        environment.name,
+        snapshot.id
+    ))? {
+        return Err(DaikokuCliError::Aborted("rollback cancelled".to_string()));
+    }
+
+    logger::loading(format!("<yellow>Rolling back</> to {}", snapshot.id));
+
+    sync_pages(&pages).await?;
+
+    // local files no longer match the remote pages, they are pushed again on the next push
+    let mut manifest = PushManifest::load(&project.path, &environment.name)?;
+    pages.iter().for_each(|page| manifest.forget(&page.path()));
+    manifest.save(&project.path, &environment.name)?;
+
+    let rollback_snapshot = PushSnapshot::new(&project.path, &environment.name, &pages);
+    rollback_snapshot.save(&project.path)?;
+
+    logger::success(format!("rollback to {} done", snapshot.id));
+    logger::json(&serde_json::json!({
+        "environment": environment.name,
+        "rollback": snapshot.id,
+        "pushed": pages.iter().map(|page| page.path()).collect::<Vec<String>>(),
+        "snapshot": rollback_snapshot.id,
+    }));
+
+    Ok(())
+}
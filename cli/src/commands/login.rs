@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
 use crate::helpers::{bytes_to_struct, daikoku_cms_api_get};
+use crate::interactive::is_non_interactive;
 use crate::logging::error::{DaikokuCliError, DaikokuResult};
 use crate::logging::logger::{self};
 use crate::{process, Commands};
@@ -27,9 +28,15 @@ struct LoginResponse {
 
 #[async_recursion]
 pub(crate) async fn run() -> DaikokuResult<()> {
+    if is_non_interactive() {
+        return Err(DaikokuCliError::Configuration(
+            "login opens a browser and cannot run in non-interactive mode. Set the DAIKOKU_COOKIE variable or run daikoku environments config --cookie=<COOKIE>".to_string(),
+        ));
+    }
+
     let environment = get_default_environment()?;
 
-    let _ = can_join_daikoku(&environment.server, None).await?;
+    let _ = can_join_daikoku(&environment).await?;
 
     let port = std::env::var("WATCHING_PORT").unwrap_or("3334".to_string());
 
@@ -44,7 +51,7 @@ pub(crate) async fn run() -> DaikokuResult<()> {
     let host = environment.server;
 
     let response: LoginResponse =
-        bytes_to_struct::<LoginResponse>(daikoku_cms_api_get("/cli/login").await?.response)?;
+        bytes_to_struct::<LoginResponse>(daikoku_cms_api_get("/cli/login").await?)?;
 
     let redirect = general_purpose::STANDARD_NO_PAD
         .encode(format!("http://localhost:{}?token={}", port, response.token).as_bytes());
@@ -53,7 +60,9 @@ pub(crate) async fn run() -> DaikokuResult<()> {
         "{}/cms-api/cli/redirect?redirect={}",
         host, redirect
     ))
-    .expect("Failed to open a new browser tab");
+    .map_err(|err| {
+        DaikokuCliError::Configuration(format!("failed to open a new browser tab : {}", err))
+    })?;
 
     loop {
         match listener.accept().await {
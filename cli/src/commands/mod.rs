@@ -1,8 +1,12 @@
 pub mod assets;
+pub mod backup;
 pub mod cms;
+pub mod diff;
 pub mod environments;
 pub mod generate;
+pub mod history;
 pub mod login;
+pub mod promote;
 pub mod pull;
 pub mod push;
 pub mod version;
@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use hyper::header;
+use mime_guess::Mime;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{unexpected_status, DaikokuClient},
+    helpers::{
+        bytes_to_vec_of_struct, raw_daikoku_cms_api_delete, raw_daikoku_cms_api_get,
+        raw_daikoku_cms_api_sync,
+    },
+    interactive::confirm,
+    logging::{
+        error::{DaikokuCliError, DaikokuResult},
+        logger,
+    },
+    models::{
+        folder::{CmsFile, Ext, SourceExtension},
+        front_matter::Metadata,
+    },
+};
+
+use super::{
+    cms::CmsPage,
+    diff::{compute_changes, print_changes, summarize_changes, ChangeSummary, PageChange},
+    environments::{get_environment_with_apikey, Environment},
+};
+
+#[derive(Serialize, Debug, Default)]
+struct PromoteSummary {
+    from: String,
+    to: String,
+    dry_run: bool,
+    changes: Vec<ChangeSummary>,
+    promoted: Vec<String>,
+    assets: Vec<String>,
+}
+
+/// Asset of the tenant, as listed by Daikoku
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct TenantAsset {
+    /// location of the asset in the bucket, ending with its id
+    key: String,
+    /// hash of the content computed by the bucket
+    #[serde(rename = "eTag")]
+    e_tag: Option<String>,
+    pub(crate) slug: Option<String>,
+    /// filename, title and description given when the asset was uploaded
+    #[serde(default)]
+    pub(crate) meta: HashMap<String, String>,
+}
+
+impl TenantAsset {
+    fn id(&self) -> &str {
+        self.key.rsplit('/').next().unwrap_or(&self.key)
+    }
+
+    /// same content, as far as the bucket can tell
+    fn same_content(&self, other: &TenantAsset) -> bool {
+        self.e_tag.is_some() && self.e_tag == other.e_tag
+    }
+}
+
+/// Copies the pages of the source environment, and optionally its assets, mails and
+/// customization, to the target environment
+pub(crate) async fn run(
+    from: String,
+    to: String,
+    assets: bool,
+    mails: bool,
+    customization: bool,
+    dry_run: bool,
+) -> DaikokuResult<()> {
+    if from == to {
+        return Err(DaikokuCliError::Configuration(
+            "the source and target environments must be different".to_string(),
+        ));
+    }
+
+    let source = get_environment_with_apikey(&from)?;
+    let target = get_environment_with_apikey(&to)?;
+
+    logger::loading(format!(
+        "<yellow>Comparing</> {} ({}) with {} ({})",
+        source.name, source.server, target.name, target.server
+    ));
+
+    let mut pages: Vec<CmsFile> = fetch_pages(&source)
+        .await?
+        .into_iter()
+        .filter(|page| is_promoted(page, mails))
+        .map(page_to_file)
+        .collect();
+
+    let mut target_pages: Vec<CmsPage> = fetch_pages(&target)
+        .await?
+        .into_iter()
+        .filter(|page| is_promoted(page, mails))
+        .collect();
+
+    if customization {
+        pages.extend(customization_to_files(
+            fetch_customization_pages(&source).await?,
+        ));
+        target_pages.extend(fetch_customization_pages(&target).await?);
+    }
+
+    let copied_assets = if assets {
+        changed_assets(fetch_assets(&source).await?, &fetch_assets(&target).await?)
+    } else {
+        vec![]
+    };
+
+    logger::done();
+
+    let changes = compute_changes(&pages, &target_pages, false);
+
+    print_changes(&changes);
+
+    copied_assets
+        .iter()
+        .for_each(|(asset, _)| logger::println(format!("<green>+ asset</> {}", asset_slug(asset))));
+
+    let slugs: Vec<String> = copied_assets
+        .iter()
+        .map(|(asset, _)| asset_slug(asset))
+        .collect();
+
+    let promoted: Vec<CmsFile> = changes
+        .iter()
+        .filter_map(|change| match change {
+            PageChange::Added(file) => Some(file.clone()),
+            PageChange::Modified { local, .. } => Some(local.clone()),
+            PageChange::Deleted(_) => None,
+        })
+        .collect();
+
+    let summary = PromoteSummary {
+        from: source.name.clone(),
+        to: target.name.clone(),
+        dry_run,
+        changes: summarize_changes(&changes),
+        promoted: promoted.iter().map(|file| file.path()).collect(),
+        assets: slugs.clone(),
+    };
+
+    if dry_run {
+        logger::success("[dry_run] promotion done".to_string());
+        logger::json(&summary);
+        return Ok(());
+    }
+
+    if promoted.is_empty() && slugs.is_empty() {
+        logger::success("nothing to promote".to_string());
+        logger::json(&summary);
+        return Ok(());
+    }
+
+    if !confirm(format!(
+        "Promote {} page(s) and {} asset(s) from {} to {} ?",
+        promoted.len(),
+        slugs.len(),
+        source.name,
+        target.name
+    ))? {
+        return Err(DaikokuCliError::Aborted("promotion cancelled".to_string()));
+    }
+
+    if !promoted.is_empty() {
+        logger::loading(format!(
+            "<yellow>Promoting</> {} page(s) to {}",
+            promoted.len(),
+            target.name
+        ));
+
+        let payload = Bytes::from(
+            serde_json::to_string(&promoted)
+                .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?,
+        );
+
+        raw_daikoku_cms_api_sync(payload, &target).await?;
+    }
+
+    if !copied_assets.is_empty() {
+        copy_assets(&source, &target, &copied_assets).await?;
+    }
+
+    logger::success(format!(
+        "promotion from {} to {} done",
+        source.name, target.name
+    ));
+    logger::json(&summary);
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_pages(environment: &Environment) -> DaikokuResult<Vec<CmsPage>> {
+    bytes_to_vec_of_struct::<CmsPage>(raw_daikoku_cms_api_get("/pages", environment).await?)
+}
+
+/// the source page as it would be read from a pulled project, keeping its id so that the page
+/// has the same id on both environments
+pub(crate) fn page_to_file(page: CmsPage) -> CmsFile {
+    let mut metadata: Metadata = page
+        .metadata
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone().into()))
+        .collect();
+
+    metadata.remove("_last_published_date");
+    metadata.insert(
+        "_path".to_string(),
+        page.path.clone().unwrap_or_default().into(),
+    );
+    metadata.insert(
+        "_content_type".to_string(),
+        page.content_type.clone().into(),
+    );
+    metadata.insert("_name".to_string(), page.name.clone().into());
+    metadata.insert(
+        "_authenticated".to_string(),
+        page.authenticated.to_string().into(),
+    );
+    metadata.insert("_visible".to_string(), page.visible.to_string().into());
+    metadata.insert("_exact".to_string(), page.exact.to_string().into());
+
+    CmsFile {
+        name: page.name,
+        content: page.content,
+        metadata,
+        daikoku_data: Some(HashMap::from([("id".to_string(), page._id)])),
+        file_path: None,
+    }
+}
+
+/// the customization pages are promoted with --customization, the mails with --mails
+fn is_promoted(page: &CmsPage, mails: bool) -> bool {
+    let path = page.path.clone().unwrap_or_default();
+
+    !path.starts_with("/customization/") && (mails || !path.starts_with("/mails/"))
+}
+
+async fn fetch_customization_pages(environment: &Environment) -> DaikokuResult<Vec<CmsPage>> {
+    Ok(customization_pages(bytes_to_vec_of_struct::<CmsPage>(
+        raw_daikoku_cms_api_get("/tenant/customization", environment).await?,
+    )?))
+}
+
+/// style, script and color theme of the tenant, named like the files of the customization folder
+fn customization_pages(pages: Vec<CmsPage>) -> Vec<CmsPage> {
+    pages
+        .into_iter()
+        .map(|page| {
+            let name = format!(
+                "{}{}",
+                page.name,
+                SourceExtension::from_content_type(&page.content_type).ext()
+            );
+
+            CmsPage {
+                path: Some(format!("/customization/{}", name)),
+                name,
+                ..page
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn customization_to_files(pages: Vec<CmsPage>) -> Vec<CmsFile> {
+    customization_pages(pages)
+        .into_iter()
+        .map(|page| {
+            let mut file = page_to_file(page);
+            // the customization pages get their id from the tenant of the target
+            file.daikoku_data = None;
+            file
+        })
+        .collect()
+}
+
+/// assets of the tenant stored under a slug, the ones without slug can't be fetched by the CLI
+pub(crate) async fn fetch_assets(environment: &Environment) -> DaikokuResult<Vec<TenantAsset>> {
+    Ok(bytes_to_vec_of_struct::<TenantAsset>(
+        raw_daikoku_cms_api_get("/tenant-assets", environment).await?,
+    )?
+    .into_iter()
+    .filter(|asset| asset.slug.is_some())
+    .collect())
+}
+
+fn asset_slug(asset: &TenantAsset) -> String {
+    asset.slug.clone().unwrap_or_default()
+}
+
+/// source assets missing on the target or with another content, with the target asset they
+/// replace
+fn changed_assets(
+    source: Vec<TenantAsset>,
+    target: &[TenantAsset],
+) -> Vec<(TenantAsset, Option<TenantAsset>)> {
+    source
+        .into_iter()
+        .filter_map(|asset| {
+            let replaced = target.iter().find(|existing| existing.slug == asset.slug);
+
+            match replaced {
+                Some(existing) if existing.same_content(&asset) => None,
+                _ => Some((asset, replaced.cloned())),
+            }
+        })
+        .collect()
+}
+
+/// downloads each asset from the source and replaces the asset with the same slug on the target
+async fn copy_assets(
+    source: &Environment,
+    target: &Environment,
+    assets: &[(TenantAsset, Option<TenantAsset>)],
+) -> DaikokuResult<()> {
+    logger::loading(format!(
+        "<yellow>Promoting</> {} asset(s) to {}",
+        assets.len(),
+        target.name
+    ));
+
+    for (asset, replaced) in assets {
+        let slug = asset_slug(asset);
+
+        let (content, content_type) = download_asset(source, &slug).await?;
+
+        upload_asset(
+            target,
+            &slug,
+            &asset.meta,
+            content,
+            content_type,
+            replaced.as_ref(),
+        )
+        .await?;
+
+        logger::indent_println(slug);
+    }
+
+    Ok(())
+}
+
+/// Stores the asset under the slug, with its filename, title and description when they are
+/// known. The asset it replaces is deleted once the upload succeeded, so that a failed upload
+/// leaves it in place.
+pub(crate) async fn upload_asset(
+    environment: &Environment,
+    slug: &str,
+    meta: &HashMap<String, String>,
+    content: Bytes,
+    content_type: Option<Mime>,
+    replaced: Option<&TenantAsset>,
+) -> DaikokuResult<()> {
+    let meta_or_slug = |key: &str| meta.get(key).map(|value| value.as_str()).unwrap_or(slug);
+
+    let client = DaikokuClient::new(environment)?;
+
+    let mut builder = client.post(client.cms_api_url("/tenant-assets")).query(&[
+        ("filename", meta_or_slug("filename")),
+        ("title", meta_or_slug("title")),
+        ("desc", meta_or_slug("desc")),
+        ("slug", slug),
+    ]);
+
+    if let Some(content_type) = content_type {
+        builder = builder.header("Asset-Content-Type", content_type.to_string());
+    }
+
+    let resp = client.send(builder.body(content)).await?;
+
+    if resp.status().as_u16() >= 300 {
+        return Err(unexpected_status(Method::POST, resp).await);
+    }
+
+    // deleted by id, the slug is now also used by the new asset
+    if let Some(replaced) = replaced {
+        raw_daikoku_cms_api_delete(&format!("/tenant-assets/{}", replaced.id()), environment)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn download_asset(
+    environment: &Environment,
+    slug: &str,
+) -> DaikokuResult<(Bytes, Option<Mime>)> {
+    let client = DaikokuClient::new(environment)?;
+
+    let resp = client
+        .send(client.get(client.cms_api_url(&format!("/tenant-assets/{}", slug))))
+        .await?;
+
+    let status = resp.status().as_u16();
+
+    if status >= 300 {
+        return Err(unexpected_status(Method::GET, resp).await);
+    }
+
+    let content_type = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<Mime>().ok());
+
+    let content = resp
+        .bytes()
+        .await
+        .map_err(|err| DaikokuCliError::Network(err.to_string()))?;
+
+    Ok((content, content_type))
+}
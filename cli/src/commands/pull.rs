@@ -1,50 +1,58 @@
-use std::{fs, io::Write, path::PathBuf, str::FromStr};
-
-use paris::Logger;
+use std::{fs, io::Write, path::PathBuf};
 
 use crate::{
     helpers::{
-        bytes_to_struct, bytes_to_vec_of_struct, daikoku_cms_api_get,
-        map_error_to_filesystem_error, raw_daikoku_cms_api_get,
+        bytes_to_struct, bytes_to_vec_of_struct, daikoku_cms_api_get, map_error_to_filesystem_error,
     },
     logging::{error::DaikokuResult, logger},
-    models::folder::{Ext, SourceExtension},
-    PullCommands,
+    models::{
+        daikokuignore::DaikokuIgnore,
+        folder::{read_sources_and_daikoku_metadata, Ext, SourceExtension, REMOTE_COPY_EXTENSION},
+        manifest::PushManifest,
+        project_manifest::{ProjectManifest, SourceFolder},
+    },
+    ConflictStrategy, PullCommands,
 };
 
 use super::{
     cms::{
-        self, create_api_folder, create_mail_folder, Api, CmsPage, IntlTranslationBody, EXCLUDE_API,
+        self, create_api_folder, create_mail_folder, create_path_and_file, format_file_content,
+        get_local_page_path, get_page_file_metadata, Api, CmsPage, IntlTranslationBody,
+        EXCLUDE_API,
     },
-    environments::{get_default_environment, read_apikey_from_secrets},
+    diff::{compute_changes, fetch_remote_pages, remote_page_label, PageChange},
+    environments::get_default_environment,
 };
 
 pub(crate) async fn run(commands: PullCommands) -> DaikokuResult<()> {
-    logger::loading(format!("<yellow>Pulling</> apis"));
+    logger::loading("<yellow>Pulling</> apis".to_string());
     logger::done();
 
     let project = cms::get_default_project()?;
 
-    match commands {
+    let pulled = match commands {
         PullCommands::Apis { id } => apis_synchronization(&project, id).await?,
         PullCommands::Mails {} => mails_synchronization(&project).await?,
         PullCommands::Customization {} => customization_synchronization(&project).await?,
+        PullCommands::Pages { conflict } => pages_synchronization(&project, conflict).await?,
     };
 
     logger::success("synchronization done".to_string());
+    logger::json(&serde_json::json!({ "pulled": pulled }));
 
     Ok(())
 }
 
-async fn apis_synchronization(project: &cms::Project, api_id: Option<String>) -> DaikokuResult<()> {
-    logger::loading(format!("<yellow>Pulling</> apis"));
+async fn apis_synchronization(
+    project: &cms::Project,
+    api_id: Option<String>,
+) -> DaikokuResult<Vec<String>> {
+    logger::loading("<yellow>Pulling</> apis".to_string());
 
-    let sources_path = PathBuf::from(project.path.clone()).join("src");
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
 
     let apis_informations: Vec<Api> = bytes_to_vec_of_struct::<Api>(
-        daikoku_cms_api_get("/apis?fields=_id,_humanReadableId,header,description")
-            .await?
-            .response,
+        daikoku_cms_api_get("/apis?fields=_id,_humanReadableId,header,description").await?,
     )?
     .into_iter()
     .filter(|api| {
@@ -53,85 +61,201 @@ async fn apis_synchronization(project: &cms::Project, api_id: Option<String>) ->
     })
     .collect();
 
-    let created = create_api_folder(apis_informations, sources_path.clone())?;
+    let created = create_api_folder(apis_informations, &project_manifest)?;
 
     if created.is_empty() {
         logger::indent_println("nothing to pull".to_string());
     }
 
-    logger::success(format!("<green>Pulling</> done"));
+    logger::success("<green>Pulling</> done".to_string());
 
-    Ok(())
+    Ok(created
+        .iter()
+        .map(|api| {
+            project_manifest
+                .folder(SourceFolder::Apis)
+                .join(api)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect())
 }
 
-async fn mails_synchronization(project: &cms::Project) -> DaikokuResult<()> {
-    logger::loading(format!("<yellow>Pulling</> mails"));
+async fn mails_synchronization(project: &cms::Project) -> DaikokuResult<Vec<String>> {
+    logger::loading("<yellow>Pulling</> mails".to_string());
 
-    let sources_path = PathBuf::from(project.path.clone()).join("src");
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
 
     let root_mail_user_translations = bytes_to_struct::<IntlTranslationBody>(
-        daikoku_cms_api_get("/translations/_mail?domain=tenant.mail.template")
-            .await?
-            .response,
+        daikoku_cms_api_get("/translations/_mail?domain=tenant.mail.template").await?,
     )?;
 
     let mail_user_template = bytes_to_struct::<IntlTranslationBody>(
-        daikoku_cms_api_get("/translations/_mail?domain=mail")
-            .await?
-            .response,
+        daikoku_cms_api_get("/translations/_mail?domain=mail").await?,
     )?;
 
-    create_mail_folder(root_mail_user_translations, sources_path.clone(), true)?;
-    create_mail_folder(mail_user_template, sources_path.clone(), false)?;
+    let mut created = create_mail_folder(root_mail_user_translations, &project_manifest, true)?;
+    created.extend(create_mail_folder(
+        mail_user_template,
+        &project_manifest,
+        false,
+    )?);
 
-    logger::success(format!("<green>Pulling</> done"));
+    logger::success("<green>Pulling</> done".to_string());
 
-    Ok(())
+    Ok(created)
 }
-async fn customization_synchronization(project: &cms::Project) -> DaikokuResult<()> {
-    logger::loading(format!("<yellow>Pulling</> customization"));
+async fn customization_synchronization(project: &cms::Project) -> DaikokuResult<Vec<String>> {
+    logger::loading("<yellow>Pulling</> customization".to_string());
 
-    let sources_path = PathBuf::from(project.path.clone())
-        .join("src")
-        .join("customization");
+    let sources_path =
+        ProjectManifest::load(&PathBuf::from(&project.path))?.folder(SourceFolder::Customization);
 
     if !sources_path.exists() {
         fs::create_dir_all(&sources_path).map_err(|err| map_error_to_filesystem_error(err, ""))?;
     }
 
-    let customization_pages = bytes_to_vec_of_struct::<CmsPage>(
-        daikoku_cms_api_get("/tenant/customization").await?.response,
-    )?;
+    let customization_pages =
+        bytes_to_vec_of_struct::<CmsPage>(daikoku_cms_api_get("/tenant/customization").await?)?;
 
     customization_pages
         .iter()
-        .for_each(|item| println!("{:?}", item.name));
+        .for_each(|item| logger::indent_println(format!("{:?}", item.name)));
+
+    let mut pulled: Vec<String> = Vec::new();
 
     customization_pages.iter().for_each(|item| {
         let file_path = sources_path.clone().join(format!(
             "{}{}",
             item.name,
-            SourceExtension::ext(&SourceExtension::from_str(&item.content_type).unwrap())
+            SourceExtension::ext(&SourceExtension::from_content_type(&item.content_type))
         ));
 
-        println!("{:?}", file_path);
+        logger::indent_println(format!("{:?}", file_path));
 
         let file = std::fs::OpenOptions::new()
             .write(true)
             .truncate(true)
             .create(true)
-            .open(file_path);
+            .open(&file_path);
 
         match file {
             Ok(mut customization_file) => {
                 let _ = customization_file.write_all(item.content.clone().as_bytes());
                 let _ = customization_file.flush();
+                pulled.push(file_path.to_string_lossy().into_owned());
             }
             Err(error) => panic!("{:#?}", error),
         }
     });
 
-    logger::success(format!("<green>Pulling</> done"));
+    logger::success("<green>Pulling</> done".to_string());
 
-    Ok(())
+    Ok(pulled)
+}
+
+async fn pages_synchronization(
+    project: &cms::Project,
+    conflict: ConflictStrategy,
+) -> DaikokuResult<Vec<String>> {
+    logger::loading("<yellow>Pulling</> pages".to_string());
+
+    let mut project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
+
+    // ignored files are read too, so that a pulled page never overwrites them
+    project_manifest.ignore = DaikokuIgnore::empty();
+
+    let local_pages = read_sources_and_daikoku_metadata(&project_manifest).check(false)?;
+
+    let remote_pages: Vec<CmsPage> = fetch_remote_pages()
+        .await?
+        .into_iter()
+        .filter(|page| {
+            !page
+                .path
+                .clone()
+                .map(|path| path.starts_with("/customization/"))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    let changes = compute_changes(&local_pages, &remote_pages, true);
+
+    let environment = get_default_environment()?;
+    let mut manifest = PushManifest::load(&project.path, &environment.name)?;
+
+    let mut pulled: Vec<String> = Vec::new();
+    let mut skipped: Vec<String> = Vec::new();
+
+    for change in changes {
+        match change {
+            PageChange::Deleted(remote) => {
+                let file_path = project_manifest.local_path(&get_local_page_path(&remote)?);
+
+                create_path_and_file(
+                    file_path.clone(),
+                    remote.content.clone(),
+                    remote.name.clone(),
+                    get_page_file_metadata(&remote),
+                    SourceExtension::from_content_type(&remote.content_type),
+                )?;
+                pulled.push(file_path.to_string_lossy().into_owned());
+            }
+            PageChange::Modified { local, remote, .. } => {
+                let file_path = match &local.file_path {
+                    Some(path) => path.clone(),
+                    None => continue,
+                };
+
+                let destination = match conflict {
+                    ConflictStrategy::Skip => {
+                        logger::indent_println(format!(
+                            "<yellow>Skipped</> {:?} has local changes",
+                            file_path
+                        ));
+                        skipped.push(remote._id.clone());
+                        continue;
+                    }
+                    ConflictStrategy::Overwrite => file_path,
+                    ConflictStrategy::Remote => {
+                        let mut remote_copy = file_path.into_os_string();
+                        remote_copy.push(format!(".{}", REMOTE_COPY_EXTENSION));
+                        PathBuf::from(remote_copy)
+                    }
+                };
+
+                let content = format_file_content(
+                    remote.content.clone(),
+                    &remote.name,
+                    &get_page_file_metadata(&remote),
+                    &SourceExtension::from_content_type(&remote.content_type),
+                )?;
+
+                logger::indent_println(format!("Writing {:?}", destination));
+
+                fs::write(&destination, content)
+                    .map_err(|err| map_error_to_filesystem_error(err, ""))?;
+                pulled.push(destination.to_string_lossy().into_owned());
+            }
+            PageChange::Added(_) => (),
+        }
+    }
+
+    // local files now hold the published version of the pages, except the skipped ones
+    remote_pages
+        .iter()
+        .filter(|page| !skipped.contains(&page._id))
+        .for_each(|page| {
+            manifest.record_published_date(remote_page_label(page), page.last_published_date)
+        });
+
+    manifest.save(&project.path, &environment.name)?;
+
+    if pulled.is_empty() {
+        logger::indent_println("nothing to pull".to_string());
+    }
+
+    logger::success("<green>Pulling</> done".to_string());
+
+    Ok(pulled)
 }
@@ -1,23 +1,53 @@
 use std::path::PathBuf;
 
 use bytes::Bytes;
+use serde::Serialize;
 
 use crate::{
-    helpers::daikoku_cms_api_post,
+    helpers::{daikoku_cms_api_delete, daikoku_cms_api_sync},
+    interactive::confirm,
     logging::{
         error::{DaikokuCliError, DaikokuResult},
         logger,
     },
-    models::folder::{read_sources_and_daikoku_metadata, CmsFile},
+    models::{
+        folder::{read_sources_and_daikoku_metadata, CmsFile},
+        history::PushSnapshot,
+        manifest::PushManifest,
+        project_manifest::ProjectManifest,
+    },
     utils::PathBufExt,
 };
 
 use super::{
     cms::{self},
-    environments::get_daikokuignore,
+    diff::{
+        compute_changes, fetch_remote_pages, print_changes, remote_page_label, summarize_changes,
+        ChangeSummary, PageChange,
+    },
+    environments::get_default_environment,
 };
 
-pub(crate) async fn run(dry_run: Option<bool>, file_path: Option<String>) -> DaikokuResult<()> {
+#[derive(Serialize, Debug, Default)]
+struct PushSummary {
+    environment: String,
+    dry_run: bool,
+    pushed: Vec<String>,
+    skipped: usize,
+    changes: Option<Vec<ChangeSummary>>,
+    conflicts: Vec<String>,
+    pruned: Vec<String>,
+    snapshot: Option<String>,
+}
+
+pub(crate) async fn run(
+    dry_run: Option<bool>,
+    file_path: Option<String>,
+    full: bool,
+    skip_invalid: bool,
+    prune: bool,
+    force: bool,
+) -> DaikokuResult<()> {
     logger::loading(format!("<yellow>Pushing</> project"));
     logger::done();
 
@@ -25,7 +55,10 @@ pub(crate) async fn run(dry_run: Option<bool>, file_path: Option<String>) -> Dai
 
     let path = PathBuf::from(project.path.clone()).join("src");
 
-    let mut local_pages = read_sources_and_daikoku_metadata(&path)?;
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
+
+    let mut local_pages =
+        read_sources_and_daikoku_metadata(&project_manifest).check(skip_invalid)?;
 
     if let Some(specific_path) = file_path {
         local_pages.retain(|file| {
@@ -49,7 +82,57 @@ pub(crate) async fn run(dry_run: Option<bool>, file_path: Option<String>) -> Dai
         }
     }
 
-    let _ = synchronization(&mut local_pages, dry_run.unwrap_or(false)).await?;
+    let environment = get_default_environment()?;
+
+    let mut manifest = PushManifest::load(&project.path, &environment.name)?;
+
+    let mut summary = PushSummary {
+        environment: environment.name.clone(),
+        dry_run: dry_run.unwrap_or(false),
+        ..PushSummary::default()
+    };
+
+    // every local page, before the unchanged ones are skipped
+    let all_local_pages = local_pages.clone();
+
+    synchronization(&mut local_pages, full, force, &mut manifest, &mut summary).await?;
+
+    let mut snapshot = None;
+
+    if !summary.dry_run && !local_pages.is_empty() {
+        let pushed = PushSnapshot::new(&project.path, &environment.name, &local_pages);
+        pushed.save(&project.path)?;
+        snapshot = Some(pushed);
+    }
+
+    // the pushed and pruned pages are recorded even when the pruning is cancelled or fails
+    let pruning = if prune {
+        prune_remote_pages(&all_local_pages, &mut manifest, &mut summary).await
+    } else {
+        Ok(())
+    };
+
+    // a rollback to this push must not bring back the pruned pages
+    if !summary.dry_run && !summary.pruned.is_empty() {
+        let pruned = snapshot
+            .get_or_insert_with(|| PushSnapshot::new(&project.path, &environment.name, &[]));
+        pruned.pruned = summary.pruned.clone();
+        pruned.save(&project.path)?;
+    }
+
+    if let Some(snapshot) = snapshot {
+        logger::info(format!(
+            "<yellow>Snapshot</> {} saved, see daikoku history",
+            snapshot.id
+        ));
+        summary.snapshot = Some(snapshot.id);
+    }
+
+    if !dry_run.unwrap_or(false) {
+        manifest.save(&project.path, &environment.name)?;
+    }
+
+    pruning?;
 
     if dry_run.unwrap_or(false) {
         logger::success("[dry_run] synchronization done".to_string());
@@ -57,42 +140,34 @@ pub(crate) async fn run(dry_run: Option<bool>, file_path: Option<String>) -> Dai
         logger::success("synchronization done".to_string());
     }
 
+    logger::json(&summary);
+
     Ok(())
 }
 
-fn apply_daikoku_ignore(items: &mut Vec<CmsFile>) -> DaikokuResult<()> {
-    let daikoku_ignore = get_daikokuignore()?;
+async fn synchronization(
+    body: &mut Vec<CmsFile>,
+    full: bool,
+    force: bool,
+    manifest: &mut PushManifest,
+    summary: &mut PushSummary,
+) -> DaikokuResult<()> {
+    logger::loading("<yellow>Syncing</>".to_string());
 
-    let rules: Vec<&String> = daikoku_ignore
-        .iter()
-        .filter(|line| !line.is_empty())
-        .collect::<Vec<&String>>();
+    let total = body.len();
 
-    if !rules.is_empty() {
-        logger::println("Excluded files and folders".to_string());
+    if !full {
+        body.retain(|file| manifest.has_changed(file));
     }
 
-    items.retain(|file| {
-        let retained = daikoku_ignore
-            .iter()
-            .filter(|line| !line.is_empty())
-            .find(|&line| {
-                line == &file.name || line == &file.path() || file.path().starts_with(line)
-            })
-            .is_none();
-
-        if !retained {
-            logger::println(file.name.to_string());
-        }
-
-        retained
-    });
+    summary.skipped = total - body.len();
 
-    Ok(())
-}
-
-async fn synchronization(body: &mut Vec<CmsFile>, dry_run: bool) -> DaikokuResult<()> {
-    logger::loading("<yellow>Syncing</>".to_string());
+    if body.len() < total {
+        logger::info(format!(
+            "{} unchanged pages skipped since the last push. Use --full to push all pages",
+            total - body.len()
+        ));
+    }
 
     logger::info(format!("Synchronization of {:?} pages", body.len()));
     body.iter().for_each(|page| {
@@ -103,15 +178,140 @@ async fn synchronization(body: &mut Vec<CmsFile>, dry_run: bool) -> DaikokuResul
         ))
     });
 
-    apply_daikoku_ignore(body)?;
+    summary.pushed = body.iter().map(|file| file.path()).collect();
 
-    let body = Bytes::from(
-        serde_json::to_string(&body)
+    if body.is_empty() && !summary.dry_run {
+        logger::println("nothing to push".to_string());
+        return Ok(());
+    }
+
+    let remote_pages = fetch_remote_pages().await?;
+    logger::done();
+    let changes = compute_changes(body, &remote_pages, false);
+
+    summary.conflicts = find_conflicts(&changes, manifest);
+    summary.conflicts.iter().for_each(|path| {
+        logger::error(format!(
+            "{} has been published on Daikoku since it was pulled",
+            path
+        ))
+    });
+
+    if summary.dry_run {
+        print_changes(&changes);
+        summary.changes = Some(summarize_changes(&changes));
+        return Ok(());
+    }
+
+    if !summary.conflicts.is_empty() {
+        if !force {
+            return Err(DaikokuCliError::Conflict(format!(
+                "{} page(s) modified on Daikoku, pull them or use --force to overwrite them",
+                summary.conflicts.len()
+            )));
+        }
+
+        logger::info(format!(
+            "<yellow>Overwriting</> {} page(s) modified on Daikoku",
+            summary.conflicts.len()
+        ));
+    }
+
+    sync_pages(body).await?;
+
+    manifest.record(body);
+
+    Ok(())
+}
+
+/// sends the pages to the environment, replacing the remote pages with the same id
+pub(crate) async fn sync_pages(pages: &[CmsFile]) -> DaikokuResult<()> {
+    let payload = Bytes::from(
+        serde_json::to_string(&pages)
             .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?,
     );
 
-    if !dry_run {
-        daikoku_cms_api_post("/sync", body, true, None).await?;
+    daikoku_cms_api_sync(payload).await?;
+
+    Ok(())
+}
+
+/// Pages that a push would overwrite although they have been published on Daikoku after the date
+/// recorded locally, in the front-matter or by the last pull. Pushed pages have no published date
+fn find_conflicts(changes: &[PageChange], manifest: &PushManifest) -> Vec<String> {
+    changes
+        .iter()
+        .filter_map(|change| match change {
+            PageChange::Modified { local, remote, .. } => {
+                let remote_date = remote.last_published_date?;
+
+                let local_date = local
+                    .metadata_text("_last_published_date")
+                    .and_then(|date| date.parse::<u64>().ok())
+                    .max(manifest.published_date(&local.path()));
+
+                if local_date.map(|date| remote_date > date).unwrap_or(true) {
+                    Some(local.path())
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Deletes the pages pushed by the CLI which match no local file, once the user has confirmed
+async fn prune_remote_pages(
+    local_pages: &[CmsFile],
+    manifest: &mut PushManifest,
+    summary: &mut PushSummary,
+) -> DaikokuResult<()> {
+    logger::loading("<yellow>Pruning</> remote pages".to_string());
+
+    let remote_pages = fetch_remote_pages().await?;
+
+    logger::done();
+
+    let orphans: Vec<_> = compute_changes(local_pages, &remote_pages, true)
+        .into_iter()
+        .filter_map(|change| match change {
+            PageChange::Deleted(page)
+                if page.metadata.get("from").map(|from| from.as_str()) == Some("cli") =>
+            {
+                Some(page)
+            }
+            _ => None,
+        })
+        .collect();
+
+    if orphans.is_empty() {
+        logger::println("no remote page to prune".to_string());
+        return Ok(());
+    }
+
+    orphans.iter().for_each(|page| {
+        logger::println(format!("<red>- deleted</> {}", remote_page_label(page)))
+    });
+
+    if summary.dry_run {
+        summary.pruned = orphans.iter().map(remote_page_label).collect();
+        return Ok(());
+    }
+
+    if !confirm(format!(
+        "Delete these {} page(s) from Daikoku ?",
+        orphans.len()
+    ))? {
+        return Err(DaikokuCliError::Aborted("pruning cancelled".to_string()));
+    }
+
+    for page in orphans {
+        daikoku_cms_api_delete(&format!("/pages/{}", page._id)).await?;
+
+        let label = remote_page_label(&page);
+        manifest.forget(&label);
+        summary.pruned.push(label);
     }
 
     Ok(())
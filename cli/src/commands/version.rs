@@ -2,5 +2,6 @@ use crate::logging::{error::DaikokuCliError, logger};
 
 pub fn run() -> Result<(), DaikokuCliError> {
     logger::success(format!("daikoku version: {}", env!("CARGO_PKG_VERSION")));
+    logger::json(&serde_json::json!({ "version": env!("CARGO_PKG_VERSION") }));
     Ok(())
 }
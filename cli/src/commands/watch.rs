@@ -1,25 +1,37 @@
 use hyper::header::{HeaderValue, LOCATION};
 use regex::Regex;
-use std::collections::HashMap;
-use std::io::Read;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::{Arc, RwLock};
 
-use http_body_util::{BodyExt, Empty, Full};
+use http_body_util::Full;
 use hyper::body::Bytes;
 use hyper::server::conn::http1;
 use hyper::service::service_fn;
 use hyper::{header, Method, StatusCode};
 use hyper::{Request, Response};
 
+use futures_util::SinkExt;
 use hyper_util::rt::TokioIo;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_tungstenite::tungstenite::{handshake::derive_accept_key, protocol::Role, Message};
+use tokio_tungstenite::WebSocketStream;
 
+use crate::client::DaikokuClient;
+use crate::interactive::is_non_interactive;
 use crate::logging::error::{DaikokuCliError, DaikokuResult};
 use crate::logging::logger::{self};
-use crate::models::folder::{read_contents, CmsFile, SourceExtension, UiCmsFile};
+use crate::models::folder::{
+    read_contents, read_sources, CmsFile, SourceExtension, UiCmsFile,
+};
+use crate::models::project_manifest::ProjectManifest;
+use crate::renderer;
+use crate::router::{Route, RouteMatch, Router};
 use crate::utils::frame_to_bytes_body;
 
 use super::cms::{self};
@@ -29,22 +41,82 @@ use super::environments::{
 
 pub(crate) const SESSION_EXPIRED: &[u8] = include_bytes!("../../templates/session_expired.html");
 const MANAGER_PAGE: &[u8] = include_bytes!("../../templates/manager.html");
+const LIVE_RELOAD_SCRIPT: &str = include_str!("../../templates/live_reload.html");
 
-#[derive(Debug, Clone)]
-struct RouterCmsPage {
-    exact: bool,
-    path: String,
+/// WebSocket endpoint notifying the served pages of the changes in the sources
+const LIVE_RELOAD_PATH: &str = "/__daikoku/live-reload";
+
+/// Pages of the project and their routes, read once when watch starts and updated on file changes
+#[derive(Clone, Debug, Default)]
+struct PagesCache {
+    pages: Arc<Vec<CmsFile>>,
+    routes: Arc<Router>,
 }
 
-#[derive(Debug)]
-struct UrlSearchParam {
-    key: String,
-    value: String,
+type SharedPagesCache = Arc<RwLock<PagesCache>>;
+
+impl PagesCache {
+    fn new(pages: Vec<CmsFile>) -> PagesCache {
+        let routes = Router::new(
+            pages
+                .iter()
+                .map(|page| Route::new(page.path(), page.exact()))
+                .collect(),
+        );
+
+        PagesCache {
+            pages: Arc::new(pages),
+            routes: Arc::new(routes),
+        }
+    }
+
+    /// replaces the pages read from the changed file, or from the files of the changed folder
+    fn update(&self, path: &Path, project_manifest: &ProjectManifest) -> PagesCache {
+        let mut pages: Vec<CmsFile> = self
+            .pages
+            .iter()
+            .filter(|page| {
+                !page
+                    .file_path
+                    .as_ref()
+                    .map(|file_path| file_path.starts_with(path))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect();
+
+        let sources = read_sources(path.to_path_buf(), project_manifest);
+
+        sources
+            .diagnostics
+            .iter()
+            .for_each(|diagnostic| logger::error(diagnostic.to_string()));
+
+        pages.extend(sources.pages);
+
+        PagesCache::new(pages)
+    }
+
+    fn snapshot(cache: &SharedPagesCache) -> PagesCache {
+        cache
+            .read()
+            .map(|cache| cache.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub(crate) struct Summary {
-    pub(crate) pages: Vec<CmsFile>,
+/// pages are rendered by the Daikoku of the environment, or locally with the fixtures
+#[derive(Clone, Debug)]
+enum RenderingMode {
+    Remote(Box<Environment>),
+    Offline(serde_json::Value),
+}
+
+/// change sent to the pages : css changes only reload the stylesheets
+#[derive(Serialize, Clone, Debug)]
+struct FileChange {
+    kind: String,
+    path: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -57,23 +129,50 @@ struct CmsRequestRendering {
 pub(crate) async fn run(
     incoming_environment: Option<String>,
     authentication: Option<bool>,
+    offline: bool,
+    fixtures: Option<String>,
+    skip_invalid: bool,
 ) -> DaikokuResult<()> {
-    let environment = check_environment_from_str(incoming_environment.clone())?;
+    let project = cms::get_default_project()?;
+
+    let mode = if offline {
+        RenderingMode::Offline(renderer::read_fixtures(&project.path, fixtures)?)
+    } else {
+        let environment = check_environment_from_str(incoming_environment.clone())?;
+
+        let _ = can_join_daikoku(&environment).await?;
+
+        RenderingMode::Remote(Box::new(environment))
+    };
 
-    let _ = can_join_daikoku(&environment.server, None).await?;
+    let (changes, _) = broadcast::channel::<String>(16);
+
+    let project_manifest = ProjectManifest::load(&PathBuf::from(&project.path))?;
+
+    let cache: SharedPagesCache = Arc::new(RwLock::new(PagesCache::new(
+        read_contents(&project_manifest).check(skip_invalid)?,
+    )));
+
+    let _sources_watcher = watch_sources(project_manifest, cache.clone(), changes.clone())?;
 
     let port = std::env::var("WATCHING_PORT").unwrap_or("3333".to_string());
 
     logger::loading(format!("<yellow>Listening</> on {}", port));
 
-    if webbrowser::open(&format!("http://localhost:{}", port)).is_ok() {}
+    // CI jobs and headless previews only need the server, the preview stays reachable when no
+    // browser can be opened
+    if !is_non_interactive() {
+        let _ = webbrowser::open(&format!("http://localhost:{}", port));
+    }
 
     let listener = TcpListener::bind(format!("0.0.0.0:{}", port))
         .await
         .unwrap();
 
     loop {
-        let environment = environment.clone();
+        let mode = mode.clone();
+        let changes = changes.clone();
+        let cache = cache.clone();
         let authentication = authentication.unwrap_or(true);
         {
             match listener.accept().await {
@@ -85,8 +184,11 @@ pub(crate) async fn run(
                         if let Err(err) = http1::Builder::new()
                             .serve_connection(
                                 io,
-                                service_fn(|req| watcher(req, &environment, authentication)),
+                                service_fn(|req| {
+                                    watcher(req, &mode, &cache, &changes, authentication)
+                                }),
                             )
+                            .with_upgrades()
                             .await
                         {
                             logger::error(format!("Error serving connection {:?}", err));
@@ -98,22 +200,149 @@ pub(crate) async fn run(
     }
 }
 
-fn read_cms_pages() -> DaikokuResult<Summary> {
-    Ok(Summary {
-        pages: read_contents(&PathBuf::from(cms::get_default_project()?.path))?,
+fn watch_sources(
+    project_manifest: ProjectManifest,
+    cache: SharedPagesCache,
+    changes: broadcast::Sender<String>,
+) -> DaikokuResult<RecommendedWatcher> {
+    let source_roots = project_manifest.source_roots();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+            ) {
+                event.paths.iter().for_each(|path| {
+                    if project_manifest.ignore.is_ignored(path, path.is_dir()) {
+                        return;
+                    }
+
+                    if let Ok(mut pages) = cache.write() {
+                        *pages = pages.update(path, &project_manifest);
+                    }
+
+                    let kind = match path.extension().and_then(|extension| extension.to_str()) {
+                        Some("css") => "css",
+                        _ => "reload",
+                    };
+
+                    let change = FileChange {
+                        kind: kind.to_string(),
+                        path: project_manifest
+                            .cms_path(path)
+                            .unwrap_or(path.to_path_buf())
+                            .to_string_lossy()
+                            .to_string(),
+                    };
+
+                    if let Ok(message) = serde_json::to_string(&change) {
+                        // no error when no page is listening
+                        let _ = changes.send(message);
+                    }
+                })
+            }
+        }
     })
+    .map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?;
+
+    for root in source_roots.iter().filter(|root| root.exists()) {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|err| DaikokuCliError::FileSystem(err.to_string()))?;
+    }
+
+    Ok(watcher)
+}
+
+fn live_reload(
+    mut req: Request<hyper::body::Incoming>,
+    changes: &broadcast::Sender<String>,
+) -> Result<Response<Full<Bytes>>, DaikokuCliError> {
+    let accept_key = match req.headers().get(header::SEC_WEBSOCKET_KEY) {
+        Some(key) => derive_accept_key(key.as_bytes()),
+        None => {
+            return Ok(Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Full::new(Bytes::from("websocket connection expected")))
+                .unwrap())
+        }
+    };
+
+    let mut receiver = changes.subscribe();
+
+    tokio::task::spawn(async move {
+        match hyper::upgrade::on(&mut req).await {
+            Err(err) => logger::error(format!("Live reload connection failed {:?}", err)),
+            Ok(upgraded) => {
+                let mut socket =
+                    WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None)
+                        .await;
+
+                loop {
+                    match receiver.recv().await {
+                        Ok(change) => {
+                            if socket.send(Message::Text(change)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::CONNECTION, "Upgrade")
+        .header(header::UPGRADE, "websocket")
+        .header(header::SEC_WEBSOCKET_ACCEPT, accept_key)
+        .body(Full::new(Bytes::new()))
+        .unwrap())
+}
+
+/// adds the live reload client at the end of the served html page
+fn inject_live_reload(html: Vec<u8>) -> Vec<u8> {
+    let html = String::from_utf8_lossy(&html).to_string();
+    let script = LIVE_RELOAD_SCRIPT.replace("{{path}}", LIVE_RELOAD_PATH);
+
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{}{}", &html[..index], script, &html[index..]),
+        None => format!("{}{}", html, script),
+    }
+    .into_bytes()
 }
 
 async fn watcher(
     req: Request<hyper::body::Incoming>,
-    environment: &Environment,
+    mode: &RenderingMode,
+    cache: &SharedPagesCache,
+    changes: &broadcast::Sender<String>,
     authentication: bool,
 ) -> Result<Response<Full<Bytes>>, DaikokuCliError> {
     let uri = req.uri().path().to_string();
 
-    if uri.starts_with("/api/") || uri.starts_with("/tenant-assets/") {
-        logger::println("forward to api or /tenant-assets".to_string());
-        forward_api_call(uri, req, environment).await
+    if uri == LIVE_RELOAD_PATH {
+        live_reload(req, changes)
+    } else if uri.starts_with("/api/") || uri.starts_with("/tenant-assets/") {
+        match mode {
+            RenderingMode::Remote(environment) => {
+                logger::println("forward to api or /tenant-assets".to_string());
+                forward_api_call(uri, req, environment).await
+            }
+            RenderingMode::Offline(_) => {
+                logger::println(format!("<yellow>Not available offline</> {}", uri));
+                Ok(Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Full::new(Bytes::from(format!(
+                        "{} is not available in offline mode",
+                        uri
+                    ))))
+                    .unwrap())
+            }
+        }
     } else {
         let path = uri.replace("_/", "");
 
@@ -125,187 +354,84 @@ async fn watcher(
 
         logger::println(format!("<green>Request received</> {}", &path));
 
-        let Summary { pages } = read_cms_pages()?;
-
-        let mut router_pages = vec![];
+        let snapshot = PagesCache::snapshot(cache);
 
-        pages.iter().for_each(|p| {
-            router_pages.push(RouterCmsPage {
-                exact: p.exact(),
-                path: p.path(),
-            })
-        });
-
-        match pages.iter().find(|page| page.path() == path) {
-            Some(page) => {
-                render_page(page, path, environment, visualizer, authentication, vec![]).await
-            }
+        match snapshot.routes.find(&path) {
             None => {
-                let (strict_page, url_search_params) =
-                    get_matching_routes(&path, get_pages(&router_pages, true), true);
-
-                let (result_page, url_search_params) = if !strict_page.is_empty() {
-                    (strict_page, url_search_params)
-                } else {
-                    get_matching_routes(&path, get_pages(&router_pages, false), false)
-                };
-
-                if result_page.is_empty() {
-                    logger::println("<red>No page found</>".to_string());
-                    Ok(Response::new("404 page not found".into()))
-                } else {
-                    match result_page.iter().nth(0) {
-                        None => {
-                            logger::println("<green>Serve 404</>".to_string());
-                            match pages.iter().find(|p| p.path() == "/404") {
-                                Some(page) => {
-                                    render_page(
-                                        page,
-                                        path,
-                                        environment,
-                                        visualizer,
-                                        authentication,
-                                        url_search_params,
-                                    )
-                                    .await
-                                }
-                                None => Ok(Response::new("404 page not found".into())),
-                            }
-                        }
-                        Some(res) => {
-                            render_page(
-                                pages.iter().find(|p| p.path() == res.path).unwrap(),
-                                path,
-                                environment,
-                                visualizer,
-                                authentication,
-                                url_search_params,
-                            )
-                            .await
-                        }
-                    }
-                }
+                logger::println("<red>No page found</>".to_string());
+                Ok(Response::new("404 page not found".into()))
             }
-        }
-    }
-}
-
-fn find_page_from_path<'a>(path: String) -> Option<CmsFile> {
-    let Summary { pages } = read_cms_pages().unwrap();
-
-    let mut router_pages = vec![];
-
-    pages.iter().for_each(|p| {
-        router_pages.push(RouterCmsPage {
-            exact: p.exact(),
-            path: p.path(),
-        })
-    });
-
-    match pages.iter().find(|page| page.path() == path) {
-        Some(page) => Some(page.clone()),
-        None => {
-            let (strict_page, params) =
-                get_matching_routes(&path, get_pages(&router_pages, true), true);
-
-            let (result_page, _params) = if !strict_page.is_empty() {
-                (strict_page, params)
-            } else {
-                get_matching_routes(&path, get_pages(&router_pages, false), false)
-            };
-
-            if result_page.is_empty() {
-                None
-            } else {
-                match result_page.iter().nth(0) {
-                    None => None,
-                    Some(res) => pages
+            Some(RouteMatch {
+                path: page_path,
+                params,
+            }) => {
+                render_page(
+                    snapshot
+                        .pages
                         .iter()
-                        .find(|p| p.path() == res.path)
-                        .map(|p| p.clone()),
-                }
+                        .find(|page| page.path() == page_path)
+                        .unwrap(),
+                    &snapshot,
+                    path,
+                    mode,
+                    visualizer,
+                    authentication,
+                    params,
+                )
+                .await
             }
         }
     }
 }
 
+fn find_page_from_path(path: String, cache: &PagesCache) -> Option<CmsFile> {
+    cache.routes.find(&path).and_then(|route| {
+        cache
+            .pages
+            .iter()
+            .find(|page| page.path() == route.path)
+            .cloned()
+    })
+}
+
 async fn forward_api_call(
     uri: String,
-    mut req: Request<hyper::body::Incoming>,
+    req: Request<hyper::body::Incoming>,
     environment: &Environment,
 ) -> Result<Response<Full<Bytes>>, DaikokuCliError> {
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
-
-    let method = req.method().to_string();
+    // the API is called with the session of the user, not with the apikey
+    let client = DaikokuClient::new(&Environment {
+        apikey: None,
+        ..environment.clone()
+    })?;
 
-    let url: String = format!("{}{}", environment.server, uri);
+    let method = req.method().clone();
 
-    let mut raw_req = Request::builder()
-        .method(Method::from_str(&method).unwrap())
-        .uri(&url)
-        .header(header::HOST, &host)
+    let mut builder = client
+        .request(method.clone(), client.url(&uri))
         .header("Accept", "*/*");
 
-
     if !uri.starts_with("/tenant-assets/") {
-        raw_req = raw_req.header(header::COOKIE, read_cookie_from_environment(true)?);
+        builder = builder.header(header::COOKIE, read_cookie_from_environment(true)?);
     }
 
-    let req = if method == "GET" {
-        raw_req.body(Empty::<Bytes>::new().boxed()).unwrap()
-    } else {
-        let mut result: Vec<u8> = Vec::new();
-
-        while let Some(next) = req.frame().await {
-            let frame = next.unwrap();
-            if let Ok(chunk) = frame.into_data() {
-                for b in chunk.bytes() {
-                    (result).push(b.unwrap().clone());
-                }
-            }
-        }
-        let body = Full::new(hyper::body::Bytes::from(result)).boxed();
-        raw_req
+    if method != Method::GET {
+        builder = builder
             .header(header::CONTENT_TYPE, "application/json")
-            .body(body)
-            .unwrap()
-    };
-
-    let stream = TcpStream::connect(&host)
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuError(err))?;
-    let io = TokioIo::new(stream);
-
-    let (mut sender, conn) = hyper::client::conn::http1::handshake(io)
-        .await
-        .map_err(|err| DaikokuCliError::HyperError(err))?;
-
-    tokio::task::spawn(async move {
-        if let Err(err) = conn.await {
-            logger::error(format!("Connection error: {:?}", err));
-        }
-    });
+            .body(frame_to_bytes_body(req.into_body()).await);
+    }
 
-    let upstream_resp = sender.send_request(req).await.map_err(|err| {
+    let upstream_resp = client.send(builder).await.map_err(|err| {
         logger::error(format!("send request failed {:?}", err));
-        DaikokuCliError::ParsingError(err.to_string())
+        err
     })?;
 
-    let (
-        hyper::http::response::Parts {
-            headers: _headers,
-            status,
-            ..
-        },
-        body,
-    ) = upstream_resp.into_parts();
+    let status = upstream_resp.status().as_u16();
 
-    let result: Vec<u8> = frame_to_bytes_body(body).await;
-
-    let status = status.as_u16();
+    let result = upstream_resp
+        .bytes()
+        .await
+        .map_err(|err| DaikokuCliError::Network(err.to_string()))?;
 
     if status >= 300 && status < 400 {
         Ok(Response::new(Full::new(Bytes::from(
@@ -313,7 +439,7 @@ async fn forward_api_call(
         ))))
     } else {
         let response = Response::builder()
-            .body(Full::new(Bytes::from(result)))
+            .body(Full::new(result))
             .unwrap();
 
         Ok(response)
@@ -322,11 +448,12 @@ async fn forward_api_call(
 
 async fn render_page(
     page: &CmsFile,
+    cache: &PagesCache,
     watch_path: String,
-    environment: &Environment,
+    mode: &RenderingMode,
     visualizer: bool,
     authentication: bool,
-    url_search_params: Vec<UrlSearchParam>,
+    params: BTreeMap<String, String>,
 ) -> Result<Response<Full<Bytes>>, DaikokuCliError> {
     logger::println(format!(
         "<green>Serve page</> {} {}",
@@ -336,15 +463,9 @@ async fn render_page(
 
     let mut current_page = page.path().clone();
 
-    let project = cms::get_default_project()?;
-
-    let mut content = read_contents(&PathBuf::from(&project.path))?;
+    let mut content = cache.pages.to_vec();
 
-    let mut fields: HashMap<String, String> = HashMap::new();
-
-    for param in url_search_params {
-        fields.insert(param.key, param.value);
-    }
+    let mut fields: HashMap<String, String> = params.into_iter().collect();
 
     if watch_path.starts_with("/mails")
         && !watch_path.starts_with("/mails/root/tenant-mail-template")
@@ -357,7 +478,7 @@ async fn render_page(
 
         let root_template = format!("/mails/root/tenant-mail-template/{}", language);
 
-        if let Some(root) = find_page_from_path(root_template) {
+        if let Some(root) = find_page_from_path(root_template, cache) {
             current_page = root.path().clone();
         }
 
@@ -379,6 +500,22 @@ async fn render_page(
             .collect();
     }
 
+    let environment = match mode {
+        RenderingMode::Remote(environment) => environment,
+        RenderingMode::Offline(fixtures) => {
+            return match renderer::render(&content, &current_page, &fields, fixtures) {
+                Ok(result) => Ok(page_response(page, &content, result.into_bytes(), visualizer)),
+                Err(err) => {
+                    logger::error(err.to_string());
+                    Ok(Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Full::new(Bytes::from(err.to_string())))
+                        .unwrap())
+                }
+            };
+        }
+    };
+
     let body_obj = CmsRequestRendering {
         content: content.clone(),
         current_page,
@@ -390,19 +527,16 @@ async fn render_page(
             .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?,
     );
 
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
+    // pages are rendered with the session of the user, not with the apikey
+    let client = DaikokuClient::new(&Environment {
+        apikey: None,
+        ..environment.as_ref().clone()
+    })?;
 
-    let url: String = format!(
-        "{}/_{}?force_reloading=true",
-        environment.server, watch_path
-    );
+    let url: String = client.url(&format!("/_{}?force_reloading=true", watch_path));
 
-    let mut builder = reqwest::Client::new()
+    let mut builder = client
         .post(url)
-        .header(header::HOST, host)
         .header(header::CONTENT_TYPE, "application/json");
 
     
@@ -428,17 +562,14 @@ async fn render_page(
         }
     }
 
-    let resp = builder
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    let resp = client.send(builder).await?;
 
     let status = resp.status().as_u16();
 
     let result: Vec<u8> = resp
         .bytes()
         .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?
+        .map_err(|err| DaikokuCliError::Network(err.to_string()))?
         .to_vec();
 
     if status == 303 {
@@ -453,164 +584,62 @@ async fn render_page(
     } else if status >= 400 {
         Ok(Response::new(Full::new(Bytes::from(result))))
     } else {
-        if !visualizer {
-            Ok(Response::builder()
-                .header(header::CONTENT_TYPE, &page.content_type())
-                .body(Full::new(Bytes::from(result)))
-                .unwrap())
-        } else {
-            let src = String::from_utf8(result).unwrap();
-
-            let source = src.replace('"', "&quot;");
-
-            let children: String = if SourceExtension::from_str(&page.content_type()).unwrap()
-                == SourceExtension::HTML
-            {
-                format!("<iframe srcdoc=\"{}\"></iframe>", source)
-            } else {
-                format!("<textarea readonly>{}</textarea>", src)
-            };
-
-            Ok(Response::builder()
-                .header(header::CONTENT_TYPE, "text/html")
-                .body(Full::new(Bytes::from(
-                    String::from_utf8(MANAGER_PAGE.to_vec())
-                        .unwrap()
-                        .replace(
-                            "{{components}}",
-                            serde_json::to_string(
-                                &content
-                                    .iter()
-                                    .map(|file| file.to_ui_component())
-                                    .collect::<Vec<UiCmsFile>>(),
-                            )
-                            .unwrap()
-                            .as_str(),
-                        )
-                        .replace("{{children}}", children.as_str()),
-                )))
-                .unwrap())
-        }
+        Ok(page_response(page, &content, result, visualizer))
     }
 }
 
-fn get_matching_routes<'a>(
-    path: &'a String,
-    cms_paths: Vec<(String, &'a RouterCmsPage)>,
-    strict_mode: bool,
-) -> (Vec<RouterCmsPage>, Vec<UrlSearchParam>) {
-    let str = path.clone().replace("/_", "").replace(".html", "");
-    let paths = str
-        .split("/")
-        .filter(|p| !p.is_empty())
-        .collect::<Vec<&str>>();
-
-    if paths.is_empty() {
-        (vec![], vec![])
-    } else {
-        let mut matched = false;
-
-        let mut formatted_paths: Vec<(Vec<String>, RouterCmsPage)> = vec![];
-
-        cms_paths.clone().iter().for_each(|r| {
-            let mut current_path: Vec<String> =
-                r.0.replace("/_/", "")
-                    .split("/")
-                    .map(String::from)
-                    .collect();
-
-            let path_suffix = if r.1.exact { "" } else { "*" }.to_string();
-
-            current_path.push(path_suffix);
-            current_path = current_path.into_iter().filter(|p| !p.is_empty()).collect();
+fn page_response(
+    page: &CmsFile,
+    content: &[CmsFile],
+    result: Vec<u8>,
+    visualizer: bool,
+) -> Response<Full<Bytes>> {
+    if !visualizer {
+        let result = if matches!(
+            SourceExtension::from_str(&page.content_type()),
+            Ok(SourceExtension::HTML)
+        ) {
+            inject_live_reload(result)
+        } else {
+            result
+        };
 
-            if !current_path.is_empty() {
-                formatted_paths.push((current_path, r.1.clone()))
-            }
-        });
+        Response::builder()
+            .header(header::CONTENT_TYPE, &page.content_type())
+            .body(Full::new(Bytes::from(result)))
+            .unwrap()
+    } else {
+        let src = String::from_utf8(result).unwrap();
 
-        formatted_paths.sort_by(|a, b| a.0.len().cmp(&b.0.len()));
+        let source = src.replace('"', "&quot;");
 
-        let mut params: Vec<UrlSearchParam> = vec![];
+        let children: String = if SourceExtension::from_content_type(&page.content_type())
+            == SourceExtension::HTML
+        {
+            format!("<iframe srcdoc=\"{}\"></iframe>", source)
+        } else {
+            format!("<textarea readonly>{}</textarea>", src)
+        };
 
-        (
-            paths
-                .iter()
-                .fold(
-                    formatted_paths,
-                    |acc: Vec<(Vec<String>, RouterCmsPage)>, path| {
-                        if acc.is_empty() || matched {
-                            acc
-                        } else {
-                            let matching_routes: Vec<(Vec<String>, RouterCmsPage)> = acc
+        Response::builder()
+            .header(header::CONTENT_TYPE, "text/html")
+            .body(Full::new(Bytes::from(inject_live_reload(
+                String::from_utf8(MANAGER_PAGE.to_vec())
+                    .unwrap()
+                    .replace(
+                        "{{components}}",
+                        serde_json::to_string(
+                            &content
                                 .iter()
-                                .filter(|p| {
-                                    if p.0.is_empty() {
-                                        false
-                                    } else {
-                                        let path_path = p.0.iter().nth(0).unwrap();
-
-                                        if path_path == path || path_path == "*" {
-                                            true
-                                        } else {
-                                            let pattern = r"\[\w+\]";
-                                            let regex: Regex = Regex::new(pattern).unwrap();
-
-                                            if let Some(cap) = regex.captures(&path_path) {
-                                                params.push(UrlSearchParam {
-                                                    value: path.to_string(),
-                                                    key: cap[0][1..cap[0].len() - 1].to_string(),
-                                                });
-
-                                                true
-                                            } else {
-                                                false
-                                            }
-                                        }
-                                    }
-                                })
-                                .map(|p| (p.0.to_vec(), p.1.clone()))
-                                .collect();
-
-                            if !matching_routes.is_empty() {
-                                matching_routes
-                                    .iter()
-                                    .map(|p| {
-                                        (p.0.to_vec().into_iter().skip(1).collect(), p.1.clone())
-                                    })
-                                    .collect()
-                            } else {
-                                match acc.iter().find(|p| p.0.is_empty()) {
-                                    Some(matching_route) if !strict_mode => {
-                                        matched = true;
-                                        let mut results = Vec::new();
-                                        results.push((
-                                            matching_route.0.to_vec(),
-                                            matching_route.1.clone(),
-                                        ));
-                                        results
-                                    }
-                                    _ => Vec::new(),
-                                }
-                            }
-                        }
-                    },
-                )
-                .into_iter()
-                .map(|page| page.1)
-                .collect(),
-            params,
-        )
+                                .map(|file| file.to_ui_component())
+                                .collect::<Vec<UiCmsFile>>(),
+                        )
+                        .unwrap()
+                        .as_str(),
+                    )
+                    .replace("{{children}}", children.as_str())
+                    .into_bytes(),
+            ))))
+            .unwrap()
     }
 }
-
-fn get_pages<'a>(
-    router_pages: &'a Vec<RouterCmsPage>,
-    exact: bool,
-) -> Vec<(String, &'a RouterCmsPage)> {
-    router_pages
-        .into_iter()
-        .filter(|p| p.exact == exact)
-        .map(|p| (p.path.clone(), p))
-        .collect()
-}
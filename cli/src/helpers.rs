@@ -1,21 +1,17 @@
 use std::any::type_name;
 
-use bytes::Buf;
+use bytes::{Buf, Bytes};
 use hyper::header;
 use mime_guess::Mime;
+use reqwest::Method;
 use serde::Deserialize;
 
 use crate::{
-    commands::environments::{get_default_environment, read_apikey_from_secrets},
+    client::{unexpected_status, DaikokuClient},
+    commands::environments::Environment,
     logging::error::{DaikokuCliError, DaikokuResult},
 };
 
-#[derive(Debug)]
-pub(crate) struct CmsApiResponse<T> {
-    pub(crate) status: u16,
-    pub(crate) response: T,
-}
-
 pub(crate) fn bytes_to_struct<T: for<'a> Deserialize<'a>>(content: Vec<u8>) -> DaikokuResult<T> {
     let name = type_name::<T>();
 
@@ -51,35 +47,74 @@ pub(crate) async fn daikoku_cms_api_post<T: Buf + std::marker::Send + 'static>(
 where
     reqwest::Body: From<T>,
 {
-    let environment = get_default_environment()?;
-
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
+    post(
+        &DaikokuClient::default_environment()?,
+        path,
+        body,
+        is_json_content,
+        content_type,
+        false,
+    )
+    .await
+}
 
-    let apikey = read_apikey_from_secrets(true)?;
+/// replaces the pages of the payload, Daikoku deleting the pages with the same id or path
+/// before saving them so that the request can be sent again
+pub(crate) async fn daikoku_cms_api_sync(payload: Bytes) -> DaikokuResult<Vec<u8>> {
+    post(
+        &DaikokuClient::default_environment()?,
+        "/sync",
+        payload,
+        true,
+        None,
+        true,
+    )
+    .await
+}
 
-    let url: String = format!("{}/cms-api{}", environment.server, &path);
+pub(crate) async fn raw_daikoku_cms_api_sync(
+    payload: Bytes,
+    environment: &Environment,
+) -> DaikokuResult<Vec<u8>> {
+    post(
+        &DaikokuClient::new(environment)?,
+        "/sync",
+        payload,
+        true,
+        None,
+        true,
+    )
+    .await
+}
 
-    let mut builder = reqwest::Client::new().post(url).header(header::HOST, host);
+async fn post<T: Buf + std::marker::Send + 'static>(
+    client: &DaikokuClient,
+    path: &str,
+    body: T,
+    is_json_content: bool,
+    content_type: Option<Mime>,
+    idempotent: bool,
+) -> DaikokuResult<Vec<u8>>
+where
+    reqwest::Body: From<T>,
+{
+    let mut builder = client.post(client.cms_api_url(path));
 
-    builder = if is_json_content {
-        builder.header(header::CONTENT_TYPE, "application/json")
-    } else {
-        builder
+    if is_json_content {
+        builder = builder.header(header::CONTENT_TYPE, "application/json");
     }
-    .header(header::AUTHORIZATION, format!("Basic {}", apikey));
 
     if let Some(content) = content_type {
         builder = builder.header("Asset-Content-Type", content.to_string());
     }
 
-    let resp = builder
-        .body(body)
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    let builder = builder.body(body);
+
+    let resp = if idempotent {
+        client.send_idempotent(builder).await?
+    } else {
+        client.send(builder).await?
+    };
 
     let status = resp.status().as_u16();
 
@@ -87,66 +122,76 @@ where
         Ok(resp
             .bytes()
             .await
-            .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?
+            .map_err(|err| DaikokuCliError::Network(err.to_string()))?
             .to_vec())
     } else {
-        Err(DaikokuCliError::DaikokuStrError(format!(
-            "failed to reach the Daikoku server {}",
-            status
-        )))
+        Err(unexpected_status(Method::POST, resp).await)
     }
 }
 
-pub(crate) async fn raw_daikoku_cms_api_get(
+pub(crate) async fn daikoku_cms_api_delete(path: &str) -> DaikokuResult<()> {
+    delete(&DaikokuClient::default_environment()?, path).await
+}
+
+pub(crate) async fn raw_daikoku_cms_api_delete(
     path: &str,
-    server: &String,
-    apikey: &String,
-) -> DaikokuResult<CmsApiResponse<Vec<u8>>> {
-    let host = server.replace("http://", "").replace("https://", "");
+    environment: &Environment,
+) -> DaikokuResult<()> {
+    delete(&DaikokuClient::new(environment)?, path).await
+}
 
-    daikoku_cms_api_get_internal(path, &server, &apikey, &host).await
+async fn delete(client: &DaikokuClient, path: &str) -> DaikokuResult<()> {
+    // deleting a page or an asset twice has no other effect
+    let resp = client
+        .send_idempotent(client.delete(client.cms_api_url(path)))
+        .await?;
+
+    let status = resp.status().as_u16();
+
+    if status < 300 {
+        Ok(())
+    } else {
+        Err(unexpected_status(Method::DELETE, resp).await)
+    }
 }
 
-pub(crate) async fn daikoku_cms_api_get(path: &str) -> DaikokuResult<CmsApiResponse<Vec<u8>>> {
-    let environment = get_default_environment()?;
+pub(crate) async fn raw_daikoku_cms_api_get(
+    path: &str,
+    environment: &Environment,
+) -> DaikokuResult<Vec<u8>> {
+    get(&DaikokuClient::new(environment)?, path).await
+}
 
-    let host = environment
-        .server
-        .replace("http://", "")
-        .replace("https://", "");
+pub(crate) async fn daikoku_cms_api_get(path: &str) -> DaikokuResult<Vec<u8>> {
+    get(&DaikokuClient::default_environment()?, path).await
+}
 
-    let apikey = read_apikey_from_secrets(true)?;
+async fn get(client: &DaikokuClient, path: &str) -> DaikokuResult<Vec<u8>> {
+    let resp = client.send(client.get(client.cms_api_url(path))).await?;
 
-    daikoku_cms_api_get_internal(path, &environment.server, &apikey, &host).await
+    let status = resp.status().as_u16();
+
+    if status < 300 {
+        Ok(resp
+            .bytes()
+            .await
+            .map_err(|err| DaikokuCliError::Network(err.to_string()))?
+            .to_vec())
+    } else {
+        Err(unexpected_status(Method::GET, resp).await)
+    }
 }
 
-async fn daikoku_cms_api_get_internal(
+/// status answered by Daikoku to a GET request, for the routes whose errors are expected
+pub(crate) async fn raw_daikoku_cms_api_status(
     path: &str,
-    server: &String,
-    apikey: &String,
-    host: &String,
-) -> DaikokuResult<CmsApiResponse<Vec<u8>>> {
-    let url: String = format!("{}/cms-api{}", server, &path);
-
-    let resp = reqwest::Client::new()
-        .get(url)
-        .header(header::HOST, host)
-        .header(header::AUTHORIZATION, format!("Basic {}", apikey))
-        .send()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?;
+    environment: &Environment,
+) -> DaikokuResult<u16> {
+    let client = DaikokuClient::new(environment)?;
 
-    let status = resp.status().as_u16();
-    let response = resp
-        .bytes()
-        .await
-        .map_err(|err| DaikokuCliError::DaikokuStrError(err.to_string()))?
-        .to_vec();
-
-    Ok(CmsApiResponse {
-        status: status,
-        response: response,
-    })
+    let resp = client.send(client.get(client.cms_api_url(path))).await?;
+
+    Ok(resp.status().as_u16())
 }
 
 pub(crate) fn map_error_to_filesystem_error<T: std::error::Error>(
@@ -1,6 +1,27 @@
-use crate::logging::error::{DaikokuCliError, DaikokuResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::logging::{
+    error::{DaikokuCliError, DaikokuResult},
+    logger,
+};
+
+static NON_INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn set_non_interactive(non_interactive: bool) {
+    NON_INTERACTIVE.store(non_interactive, Ordering::SeqCst);
+}
+
+pub(crate) fn is_non_interactive() -> bool {
+    NON_INTERACTIVE.load(Ordering::SeqCst)
+}
 
 pub(crate) fn prompt() -> DaikokuResult<String> {
+    if is_non_interactive() {
+        return Err(DaikokuCliError::Configuration(
+            "an input is required but the CLI runs in non-interactive mode".to_string(),
+        ));
+    }
+
     let mut input = String::new();
 
     match std::io::stdin().read_line(&mut input) {
@@ -8,3 +29,14 @@ pub(crate) fn prompt() -> DaikokuResult<String> {
         Ok(_) => Ok(input),
     }
 }
+
+/// asks a [yN] question, always answered yes in non-interactive mode
+pub(crate) fn confirm(question: String) -> DaikokuResult<bool> {
+    if is_non_interactive() {
+        return Ok(true);
+    }
+
+    logger::error(format!("{} [yN]", question));
+
+    Ok(prompt()?.trim() == "y")
+}
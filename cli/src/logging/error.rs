@@ -13,7 +13,128 @@ pub enum DaikokuCliError {
     DaikokuStrError(String),
     DaikokuErrorWithMessage(String, std::io::Error),
     DaikokuYamlError(serde_yaml::Error),
-    ParsingError(String)
+    ParsingError(String),
+    /// Daikoku can't be reached, or the connection was lost
+    Network(String),
+    /// the apikey or the session was refused
+    Authentication(String),
+    /// the content of Daikoku has changed since it was pulled
+    Conflict(String),
+    /// a confirmation was declined
+    Aborted(String),
+    /// Daikoku answered with an unexpected status
+    Http {
+        status: u16,
+        method: String,
+        url: String,
+        server_message: Option<String>,
+    },
+}
+
+/// exit codes of the CLI, stable across releases so that scripts can rely on them
+pub(crate) const EXIT_FAILURE: i32 = 1;
+pub(crate) const EXIT_CONFIGURATION: i32 = 3;
+pub(crate) const EXIT_FILE_SYSTEM: i32 = 4;
+pub(crate) const EXIT_NETWORK: i32 = 5;
+pub(crate) const EXIT_AUTHENTICATION: i32 = 6;
+pub(crate) const EXIT_SERVER_VALIDATION: i32 = 7;
+pub(crate) const EXIT_CONFLICT: i32 = 8;
+pub(crate) const EXIT_ABORTED: i32 = 9;
+
+/// printed at the end of --help, the code 2 is the one of clap for invalid arguments
+pub(crate) const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  unexpected failure
+  2  invalid arguments
+  3  configuration error: missing or invalid project, environment or setting
+  4  file system error: a file can't be read or written
+  5  network error: Daikoku can't be reached or is unavailable (5xx, 408, 429), safe to retry
+  6  authentication error: the apikey or the session was refused (401, 403, expired session)
+  7  server validation error: Daikoku rejected the request (400, 404, 413, 422, ...)
+  8  conflict: the content has changed on Daikoku (409, pages modified since the last pull)
+  9  aborted: a confirmation was declined";
+
+/// longest error body of Daikoku kept in the message, the remaining is cut
+const SERVER_MESSAGE_MAX_LENGTH: usize = 500;
+
+impl DaikokuCliError {
+    pub(crate) fn exit_code(&self) -> i32 {
+        match self {
+            DaikokuCliError::Configuration(_) => EXIT_CONFIGURATION,
+            DaikokuCliError::CmsCreationFile(_)
+            | DaikokuCliError::FileSystem(_)
+            | DaikokuCliError::DaikokuError(_)
+            | DaikokuCliError::DaikokuErrorWithMessage(_, _) => EXIT_FILE_SYSTEM,
+            DaikokuCliError::Network(_) | DaikokuCliError::HyperError(_) => EXIT_NETWORK,
+            DaikokuCliError::Authentication(_) => EXIT_AUTHENTICATION,
+            DaikokuCliError::Conflict(_) => EXIT_CONFLICT,
+            DaikokuCliError::Aborted(_) => EXIT_ABORTED,
+            DaikokuCliError::Http { status, .. } => match status {
+                300..=399 | 401 | 403 => EXIT_AUTHENTICATION,
+                408 | 429 | 500..=599 => EXIT_NETWORK,
+                409 => EXIT_CONFLICT,
+                _ => EXIT_SERVER_VALIDATION,
+            },
+            DaikokuCliError::DaikokuStrError(_)
+            | DaikokuCliError::DaikokuYamlError(_)
+            | DaikokuCliError::ParsingError(_) => EXIT_FAILURE,
+        }
+    }
+
+    pub(crate) fn http(status: u16, method: &str, url: &str, body: &[u8]) -> DaikokuCliError {
+        DaikokuCliError::Http {
+            status,
+            method: method.to_string(),
+            url: url.to_string(),
+            server_message: server_message(body),
+        }
+    }
+}
+
+/// message of a Daikoku error body, like {"error": "cms page not found"} or
+/// {"error": "Error while parsing payload", "msg": "..."}
+fn server_message(body: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(body).trim().to_string();
+
+    let message = match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(serde_json::Value::Object(content)) => {
+            let field = |name: &str| match content.get(name) {
+                Some(serde_json::Value::String(value)) => Some(value.clone()),
+                Some(serde_json::Value::Null) | Some(serde_json::Value::Bool(_)) | None => None,
+                Some(value) => Some(value.to_string()),
+            };
+
+            match (field("error").or(field("message")), field("msg")) {
+                (Some(error), Some(msg)) => Some(format!("{} : {}", error, msg)),
+                (error, msg) => error.or(msg),
+            }
+        }
+        Ok(serde_json::Value::String(value)) => Some(value),
+        // html pages of the errors aren't readable in a terminal
+        _ if text.starts_with('<') => None,
+        _ => Some(text),
+    };
+
+    message
+        .filter(|message| !message.is_empty())
+        .map(
+            |message| match message.char_indices().nth(SERVER_MESSAGE_MAX_LENGTH) {
+                Some((index, _)) => format!("{}...", &message[..index]),
+                None => message,
+            },
+        )
+}
+
+/// what to do after the common failures
+fn http_hint(status: u16) -> Option<&'static str> {
+    match status {
+        401 => Some("the apikey or the session was refused, check the apikey with daikoku environments config --apikey=<APIKEY> or run daikoku login"),
+        403 => Some("the apikey lacks CMS rights, use an apikey subscribed to the CMS API of the tenant"),
+        404 => Some("check the server of the environment with daikoku environments info, and the path or slug used"),
+        409 => Some("the content has changed on Daikoku, pull it before pushing again"),
+        413 => Some("the request is too large for Daikoku or for a proxy in front of it, send smaller files"),
+        _ => None,
+    }
 }
 
 impl Error for DaikokuCliError {}
@@ -57,6 +178,33 @@ impl fmt::Display for DaikokuCliError {
             DaikokuCliError::ParsingError(err) => {
                 write!(f, "[DAIKOKU] : {}\n", &err)
             }
+            DaikokuCliError::Network(err) => {
+                writeln!(f, "[NETWORK] : {}", &err)
+            }
+            DaikokuCliError::Authentication(err) => {
+                writeln!(f, "[AUTHENTICATION] : {}", &err)
+            }
+            DaikokuCliError::Conflict(err) => {
+                writeln!(f, "[CONFLICT] : {}", &err)
+            }
+            DaikokuCliError::Aborted(err) => {
+                writeln!(f, "[ABORTED] : {}", &err)
+            }
+            DaikokuCliError::Http {
+                status,
+                method,
+                url,
+                server_message,
+            } => {
+                write!(f, "[HTTP {}] : {} {}", status, method, url)?;
+                if let Some(message) = server_message {
+                    write!(f, " - {}", message)?;
+                }
+                if let Some(hint) = http_hint(*status) {
+                    write!(f, "\n{}", hint)?;
+                }
+                writeln!(f)
+            }
         }
     }
 }
@@ -1,28 +1,93 @@
-use once_cell::sync::Lazy;
+use std::sync::Mutex;
+
+use once_cell::sync::{Lazy, OnceCell};
 use paris::Logger;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::OutputFormat;
 
 static mut LOADING_LOGGER: once_cell::sync::Lazy<Logger> = Lazy::new(|| Logger::new());
 static mut LOADING_MESSAGE: once_cell::sync::Lazy<Option<String>> =
     Lazy::new(|| Some(String::new()));
 
+static OUTPUT_FORMAT: OnceCell<OutputFormat> = OnceCell::new();
+static RESULT: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+static STYLE_TAGS: Lazy<Regex> = Lazy::new(|| Regex::new(r"</?[a-z]*>").unwrap());
+
+pub fn set_output_format(format: OutputFormat) {
+    let _ = OUTPUT_FORMAT.set(format);
+}
+
+pub fn is_json() -> bool {
+    matches!(OUTPUT_FORMAT.get(), Some(OutputFormat::Json))
+}
+
+/// in json mode, progress messages are written without colors on stderr to keep stdout parsable
+fn to_stderr(str: &str) {
+    eprintln!("{}", STYLE_TAGS.replace_all(str, ""));
+}
+
+/// keeps the result of a command, written on stdout by flush_json once the command succeeded,
+/// only in json mode
+pub fn json<T: Serialize>(value: &T) {
+    if is_json() {
+        *RESULT.lock().unwrap() =
+            Some(serde_json::to_string_pretty(value).unwrap_or("null".to_string()));
+    }
+}
+
+/// writes the result of the command, or the given value when the command has no result, so that
+/// stdout always holds a single JSON document
+pub fn flush_json<T: Serialize>(fallback: &T) {
+    if is_json() {
+        let result = RESULT
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or(serde_json::to_string_pretty(fallback).unwrap_or("null".to_string()));
+        println!("{}", result);
+    }
+}
+
+/// drops the result of a command which failed after giving it
+pub fn discard_json() {
+    RESULT.lock().unwrap().take();
+}
+
 pub fn println(str: String) {
+    if is_json() {
+        to_stderr(&str.replace("\n", ""));
+        return;
+    }
     check_loading();
     let mut logger = Logger::new();
     logger.info(str.replace("\n", ""));
 }
 
 pub fn info(str: String) {
+    if is_json() {
+        to_stderr(&str);
+        return;
+    }
     let mut logger = Logger::new();
     logger.info(str);
 }
 
 pub fn indent_println(str: String) {
+    if is_json() {
+        to_stderr(&format!("  {}", str.replace("\n", "")));
+        return;
+    }
     check_loading();
     let mut logger = Logger::new();
     logger.indent(1).info(str.replace("\n", ""));
 }
 
 pub fn check_loading() {
+    if is_json() {
+        return;
+    }
     unsafe {
         if (*LOADING_MESSAGE).is_some() {
             LOADING_LOGGER.done();
@@ -36,6 +101,9 @@ pub fn check_loading() {
 }
 
 pub fn done() {
+    if is_json() {
+        return;
+    }
     unsafe {
         LOADING_LOGGER.done();
         *LOADING_MESSAGE = None;
@@ -43,12 +111,22 @@ pub fn done() {
 }
 
 pub fn error(str: String) {
+    if is_json() {
+        to_stderr(&str);
+        return;
+    }
     check_loading();
     let mut logger = Logger::new();
     logger.error(str);
 }
 
 pub fn success(str: String) {
+    if is_json() {
+        if !str.is_empty() {
+            to_stderr(&str);
+        }
+        return;
+    }
     unsafe {
         LOADING_LOGGER.done();
         *LOADING_MESSAGE = None;
@@ -58,6 +136,10 @@ pub fn success(str: String) {
 }
 
 pub fn loading(str: String) {
+    if is_json() {
+        to_stderr(&str);
+        return;
+    }
     check_loading();
     unsafe {
         LOADING_LOGGER.loading(&str);
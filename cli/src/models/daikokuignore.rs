@@ -0,0 +1,297 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use once_cell::sync::Lazy;
+
+use crate::logging::{
+    error::{DaikokuCliError, DaikokuResult},
+    logger,
+};
+
+/// legacy rules already reported as deprecated
+static WARNED_RULES: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Rules of the `.daikoku/.daikokuignore` file and of the daikoku.toml file. They follow the
+/// gitignore syntax, and anchored rules are relative to the project root
+#[derive(Debug, Clone)]
+pub(crate) struct DaikokuIgnore {
+    root: PathBuf,
+    matcher: Gitignore,
+    /// rules of the .daikokuignore file matching nothing with the gitignore syntax, which are
+    /// read with the syntax of the previous versions of the CLI, see `is_ignored_by_legacy_rule`
+    legacy: Vec<String>,
+}
+
+impl DaikokuIgnore {
+    pub(crate) fn load(project_path: &Path, rules: &[String]) -> DaikokuResult<DaikokuIgnore> {
+        let file = project_path.join(".daikoku").join(".daikokuignore");
+
+        let mut builder = GitignoreBuilder::new(project_path);
+
+        let mut legacy = vec![];
+
+        if file.exists() {
+            if let Some(err) = builder.add(&file) {
+                return Err(DaikokuCliError::Configuration(format!(
+                    "invalid rule in {} : {}",
+                    file.display(),
+                    err
+                )));
+            }
+
+            legacy = fs::read_to_string(&file)
+                .map(|content| {
+                    content
+                        .lines()
+                        .map(|line| line.trim())
+                        .filter(|line| is_legacy_rule(line))
+                        // anchored rules without glob only match the path they name
+                        .filter(|line| !project_path.join(line.trim_matches('/')).exists())
+                        .map(|line| line.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+
+        for rule in rules {
+            builder.add_line(None, rule).map_err(|err| {
+                DaikokuCliError::Configuration(format!("invalid ignore rule {} : {}", rule, err))
+            })?;
+        }
+
+        let matcher = builder.build().map_err(|err| {
+            DaikokuCliError::Configuration(format!("invalid rule in {} : {}", file.display(), err))
+        })?;
+
+        Ok(DaikokuIgnore {
+            root: project_path.to_path_buf(),
+            matcher,
+            legacy,
+        })
+    }
+
+    /// no file is excluded
+    pub(crate) fn empty() -> DaikokuIgnore {
+        DaikokuIgnore {
+            root: PathBuf::new(),
+            matcher: Gitignore::empty(),
+            legacy: vec![],
+        }
+    }
+
+    /// whether the file or folder is excluded, by itself or by one of its parent folders
+    pub(crate) fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.matcher.is_empty() {
+            return false;
+        }
+
+        match path.strip_prefix(&self.root) {
+            Ok(relative_path) if relative_path.as_os_str().is_empty() => false,
+            Ok(relative_path) => self
+                .matcher
+                .matched_path_or_any_parents(relative_path, is_dir)
+                .is_ignore(),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether a file is excluded by a rule written for the previous versions of the CLI, which
+    /// matched the start of the page path on Daikoku, like `/drafts`, or of the path in the CMS,
+    /// like `pages/drafts`, segment by segment. A negation of the gitignore syntax matching the
+    /// file still includes it. Each rule is reported once as deprecated.
+    pub(crate) fn is_ignored_by_legacy_rule(
+        &self,
+        path: &Path,
+        route: &str,
+        cms_path: &str,
+    ) -> bool {
+        let route = route.trim_start_matches('/');
+
+        let Some(rule) = self.legacy.iter().find(|rule| {
+            let prefix = rule.trim_matches('/');
+            starts_with_segments(route, prefix) || starts_with_segments(cms_path, prefix)
+        }) else {
+            return false;
+        };
+
+        if self.is_whitelisted(path) {
+            return false;
+        }
+
+        if WARNED_RULES.lock().unwrap().insert(rule.clone()) {
+            logger::info(format!(
+                "<yellow>Deprecated</> the .daikokuignore rule {} excludes /{} with the syntax of \
+                 the previous versions, write it relative to the project root",
+                rule, route
+            ));
+        }
+
+        true
+    }
+
+    fn is_whitelisted(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.root) {
+            Ok(relative_path) => self
+                .matcher
+                .matched_path_or_any_parents(relative_path, false)
+                .is_whitelist(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// rule anchored to the project root, without glob nor negation, which the previous versions of
+/// the CLI compared to the path of the pages
+fn is_legacy_rule(line: &str) -> bool {
+    !line.starts_with('#')
+        && !line.starts_with('!')
+        && !line.contains(['*', '?', '['])
+        && line.trim_end_matches('/').contains('/')
+}
+
+/// whether the path is the prefix or one of its children
+fn starts_with_segments(path: &str, prefix: &str) -> bool {
+    path == prefix || path.starts_with(&format!("{}/", prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// project with the .daikokuignore file and the given files
+    fn project(rules: &str, files: &[&str]) -> (PathBuf, DaikokuIgnore) {
+        let root = std::env::temp_dir().join(format!("daikoku-ignore-{}", uuid::Uuid::new_v4()));
+
+        fs::create_dir_all(root.join(".daikoku")).unwrap();
+        fs::write(root.join(".daikoku").join(".daikokuignore"), rules).unwrap();
+
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let ignore = DaikokuIgnore::load(&root, &[]).unwrap();
+
+        (root, ignore)
+    }
+
+    fn is_ignored_page(ignore: &DaikokuIgnore, root: &Path, route: &str, cms_path: &str) -> bool {
+        let path = root.join("src").join(cms_path);
+        ignore.is_ignored(&path, false) || ignore.is_ignored_by_legacy_rule(&path, route, cms_path)
+    }
+
+    #[test]
+    fn gitignore_rules() {
+        let (root, ignore) = project(
+            "src/pages/drafts/\n*.wip.html\n!keep.wip.html\n",
+            &["src/pages/drafts/page.html"],
+        );
+
+        assert!(ignore.legacy.is_empty());
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts",
+            "pages/drafts/page.html"
+        ));
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/a.wip",
+            "pages/a.wip.html"
+        ));
+        assert!(!is_ignored_page(
+            &ignore,
+            &root,
+            "/keep.wip",
+            "pages/keep.wip.html"
+        ));
+    }
+
+    #[test]
+    fn legacy_rules() {
+        let (root, ignore) = project("/drafts\npages/old.html\n", &[]);
+
+        assert_eq!(ignore.legacy, vec!["/drafts", "pages/old.html"]);
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts",
+            "pages/drafts/page.html"
+        ));
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts/first",
+            "pages/drafts/first.html"
+        ));
+        assert!(is_ignored_page(&ignore, &root, "/old", "pages/old.html"));
+        assert!(!is_ignored_page(&ignore, &root, "/kept", "pages/kept.html"));
+    }
+
+    #[test]
+    fn legacy_rules_match_whole_segments() {
+        let (root, ignore) = project("/doc\n", &[]);
+
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/doc/intro",
+            "pages/doc/intro.html"
+        ));
+        assert!(!is_ignored_page(
+            &ignore,
+            &root,
+            "/documentation",
+            "pages/documentation.html"
+        ));
+    }
+
+    #[test]
+    fn rules_matching_a_file_are_not_legacy() {
+        let (root, ignore) = project(
+            "/drafts\nsrc/pages/old.html\n",
+            &["drafts/notes.md", "src/pages/old.html"],
+        );
+
+        assert!(ignore.legacy.is_empty());
+        assert!(!is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts",
+            "pages/drafts/page.html"
+        ));
+        assert!(is_ignored_page(&ignore, &root, "/old", "pages/old.html"));
+    }
+
+    #[test]
+    fn negation_includes_legacy_matches() {
+        let (root, ignore) = project("/drafts\n!src/pages/drafts/keep.html\n", &[]);
+
+        assert!(is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts/other",
+            "pages/drafts/other.html"
+        ));
+        assert!(!is_ignored_page(
+            &ignore,
+            &root,
+            "/drafts/keep",
+            "pages/drafts/keep.html"
+        ));
+    }
+
+    #[test]
+    fn only_anchored_rules_without_glob_are_legacy() {
+        let (_, ignore) = project("drafts/\n/pages/*.wip\n!/pages/keep\n# /pages/old\n", &[]);
+
+        assert!(ignore.legacy.is_empty());
+    }
+}
@@ -4,22 +4,38 @@ use walkdir::WalkDir;
 
 use std::{
     collections::HashMap,
+    fmt,
     fs::{self},
-    path::PathBuf,
+    io::ErrorKind,
+    path::{Path, PathBuf},
     str::FromStr,
 };
 
 use crate::{
-    logging::error::{DaikokuCliError, DaikokuResult},
+    logging::{
+        error::{DaikokuCliError, DaikokuResult},
+        logger,
+    },
     utils::new_custom_ini_file,
 };
 
+use super::{
+    daikokuignore::DaikokuIgnore,
+    front_matter::{self, FrontMatter, Metadata},
+    project_manifest::{ProjectManifest, SourceFolder},
+};
+
+/// extension of the copies written by `daikoku pull pages --conflict=remote`, never read as sources
+pub(crate) const REMOTE_COPY_EXTENSION: &str = "remote";
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub(crate) struct CmsFile {
     pub(crate) name: String,
     pub(crate) content: String,
-    pub(crate) metadata: HashMap<String, String>,
+    pub(crate) metadata: Metadata,
     pub(crate) daikoku_data: Option<HashMap<String, String>>,
+    #[serde(skip)]
+    pub(crate) file_path: Option<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -29,15 +45,6 @@ pub(crate) struct UiCmsFile {
 }
 
 impl CmsFile {
-    fn new(file_path: PathBuf, file_name: String, metadata: HashMap<String, String>) -> CmsFile {
-        CmsFile {
-            content: fs::read_to_string(file_path).unwrap(),
-            name: file_name,
-            metadata,
-            daikoku_data: None,
-        }
-    }
-
     pub(crate) fn to_ui_component(&self) -> UiCmsFile {
         UiCmsFile {
             name: self.name.clone(),
@@ -45,16 +52,17 @@ impl CmsFile {
         }
     }
 
+    /// value of a metadata as text, see `front_matter::metadata_text`
+    pub(crate) fn metadata_text(&self, key: &str) -> Option<String> {
+        self.metadata.get(key).map(front_matter::metadata_text)
+    }
+
     pub(crate) fn path(&self) -> String {
-        self.metadata
-            .get("_path")
-            .cloned()
-            .unwrap_or("".to_string())
+        self.metadata_text("_path").unwrap_or("".to_string())
     }
 
     fn bool(&self, key: String) -> bool {
-        self.metadata
-            .get(&key)
+        self.metadata_text(&key)
             .map(|str| str.parse().unwrap_or(false))
             .unwrap_or(false)
     }
@@ -67,178 +75,307 @@ impl CmsFile {
         self.bool("_authenticated".to_string())
     }
 
-    pub(crate) fn content_type(&self) -> String {
-        self.metadata
-            .get("_content_type")
+    /// identifier of the page on Daikoku, when it isn't derived from the path : the id of the
+    /// .daikoku_data file or the `id` metadata
+    pub(crate) fn id(&self) -> Option<String> {
+        self.daikoku_data
+            .as_ref()
+            .and_then(|data| data.get("id"))
             .cloned()
+            .or_else(|| self.metadata_text("id"))
+    }
+
+    pub(crate) fn content_type(&self) -> String {
+        self.metadata_text("_content_type")
             .unwrap_or("".to_string())
     }
 }
 
-pub fn read_contents(path: &PathBuf) -> DaikokuResult<Vec<CmsFile>> {
-    read_sources(path.join("src"))
+/// File of the project that can't be read as a source
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Diagnostic {
+    pub(crate) path: PathBuf,
+    pub(crate) line: Option<usize>,
+    pub(crate) reason: String,
 }
 
-pub(crate) fn read_sources(path: PathBuf) -> DaikokuResult<Vec<CmsFile>> {
-    let mut pages: Vec<CmsFile> = Vec::new();
+impl Diagnostic {
+    pub(crate) fn new(path: &Path, reason: impl ToString) -> Diagnostic {
+        Diagnostic {
+            path: path.to_path_buf(),
+            line: None,
+            reason: reason.to_string(),
+        }
+    }
 
-    for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
-        let f_name = String::from(entry.file_name().to_string_lossy());
+    pub(crate) fn at_line(path: &Path, line: usize, reason: impl ToString) -> Diagnostic {
+        Diagnostic {
+            line: Some(line),
+            ..Diagnostic::new(path, reason)
+        }
+    }
+}
 
-        if entry.metadata().unwrap().is_file() {
-            if let Some(extension) = entry.clone().path().extension() {
-                let new_file = read_file(
-                    entry.clone().into_path(),
-                    f_name,
-                    extension.to_string_lossy().into_owned(),
-                );
-                pages.push(new_file);
-            }
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{} : {}", self.path.display(), line, self.reason),
+            None => write!(f, "{} : {}", self.path.display(), self.reason),
         }
     }
+}
 
-    Ok(pages)
+/// Pages read from the sources, with a diagnostic for each file that couldn't be read
+#[derive(Debug, Default)]
+pub(crate) struct Sources {
+    pub(crate) pages: Vec<CmsFile>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
 }
 
-pub(crate) fn read_sources_and_daikoku_metadata(path: &PathBuf) -> DaikokuResult<Vec<CmsFile>> {
-    let mut pages: Vec<CmsFile> = Vec::new();
+impl Sources {
+    fn add(&mut self, file: Result<Option<CmsFile>, Diagnostic>) {
+        match file {
+            Ok(Some(page)) => self.pages.push(page),
+            Ok(None) => (),
+            Err(diagnostic) => self.diagnostics.push(diagnostic),
+        }
+    }
 
-    let paths = fs::read_dir(path).unwrap();
+    fn extend(&mut self, sources: Sources) {
+        self.pages.extend(sources.pages);
+        self.diagnostics.extend(sources.diagnostics);
+    }
+
+    /// Reports every invalid file at once, then fails unless the invalid files are skipped
+    pub(crate) fn check(self, skip_invalid: bool) -> DaikokuResult<Vec<CmsFile>> {
+        if self.diagnostics.is_empty() {
+            warn_duplicate_ids(&self.pages);
+            return Ok(self.pages);
+        }
 
-    for raw_path in paths {
-        let directory_name = raw_path.unwrap().file_name().into_string().unwrap();
-        if directory_name != ".DS_Store" {
-            let sources = if directory_name == "apis" {
-                read_apis_folder(&path.join("apis"))
-            } else {
-                read_sources(path.join(directory_name))
-            }?;
-            pages.extend(sources);
+        self.diagnostics
+            .iter()
+            .for_each(|diagnostic| logger::error(diagnostic.to_string()));
+
+        if skip_invalid {
+            warn_duplicate_ids(&self.pages);
+            logger::info(format!(
+                "<yellow>Skipped</> {} invalid file(s)",
+                self.diagnostics.len()
+            ));
+            Ok(self.pages)
+        } else {
+            Err(DaikokuCliError::FileSystem(format!(
+                "{} invalid file(s) in the project, fix them or use --skip-invalid to ignore them",
+                self.diagnostics.len()
+            )))
         }
     }
+}
 
-    for page in pages.iter_mut() {
-        if !page.path().starts_with("/") {
-            page.metadata
-                .insert("_path".to_string(), format!("/{}", page.path()));
+/// pages sharing an id replace each other on Daikoku, only the last pushed one is kept
+fn warn_duplicate_ids(pages: &[CmsFile]) {
+    let mut paths_by_id: HashMap<String, Vec<String>> = HashMap::new();
+
+    pages.iter().for_each(|page| {
+        if let Some(id) = page.id() {
+            paths_by_id.entry(id).or_default().push(page.path());
         }
+    });
+
+    let mut duplicates: Vec<(String, Vec<String>)> = paths_by_id
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    duplicates.sort();
+
+    duplicates.iter().for_each(|(id, paths)| {
+        logger::info(format!(
+            "<yellow>Duplicate id</> {} is used by {}",
+            id,
+            paths.join(", ")
+        ))
+    });
+}
+
+pub fn read_contents(manifest: &ProjectManifest) -> Sources {
+    let mut sources = Sources::default();
+
+    for root in manifest.source_roots() {
+        sources.extend(read_sources(root, manifest));
     }
 
-    Ok(pages)
+    sources
 }
 
-fn read_apis_folder(path: &PathBuf) -> DaikokuResult<Vec<CmsFile>> {
-    let mut pages: Vec<CmsFile> = Vec::new();
+/// reads all files under a path, which can also be a single file. A missing path has no sources
+pub(crate) fn read_sources(path: PathBuf, manifest: &ProjectManifest) -> Sources {
+    let mut sources = Sources::default();
 
-    let apis = fs::read_dir(path).unwrap();
+    for entry in walk(&path, &manifest.ignore) {
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_file() {
+                    sources.add(read_source_file(entry.path(), manifest));
+                }
+            }
+            Err(err) => {
+                let not_found = err
+                    .io_error()
+                    .map(|err| err.kind() == ErrorKind::NotFound)
+                    .unwrap_or(false);
+
+                if !not_found {
+                    sources
+                        .diagnostics
+                        .push(Diagnostic::new(err.path().unwrap_or(&path), &err));
+                }
+            }
+        }
+    }
 
-    for raw_path in apis {
-        let directory_name = raw_path.unwrap().file_name().into_string().unwrap();
+    sources
+}
 
-        if !directory_name.starts_with(".") {
-            let sources = read_api_folder(&path.join(directory_name))?;
-            pages.extend(sources);
+/// reads a single file of the sources, files without extension and remote copies are ignored
+fn read_source_file(path: &Path, manifest: &ProjectManifest) -> Result<Option<CmsFile>, Diagnostic> {
+    let (Some(extension), Some(f_name)) = (
+        path.extension()
+            .filter(|extension| *extension != REMOTE_COPY_EXTENSION),
+        path.file_name(),
+    ) else {
+        return Ok(None);
+    };
+
+    let file_name = f_name.to_string_lossy().to_string();
+
+    if let Some(cms_path) = manifest.cms_path(path) {
+        let route = page_route(&cms_path, &file_name);
+
+        if manifest
+            .ignore
+            .is_ignored_by_legacy_rule(path, &route, &cms_path.to_string_lossy())
+        {
+            return Ok(None);
         }
     }
 
-    Ok(pages)
+    read_file(
+        path.to_path_buf(),
+        file_name,
+        extension.to_string_lossy().into_owned(),
+        manifest,
+    )
+    .map(Some)
 }
 
-fn read_api_folder(path: &PathBuf) -> DaikokuResult<Vec<CmsFile>> {
-    let mut pages: Vec<CmsFile> = Vec::new();
-
-    let mut daikoku_data = None;
-
-    for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
-        let f_name = String::from(entry.file_name().to_string_lossy());
-
-        if entry.metadata().unwrap().is_file() {
-            if entry.clone().file_name() == ".daikoku_data" {
-                let daikoku_data_path = entry.clone().path().to_path_buf();
-
-                let daikoku_data_file = read_file(
-                    daikoku_data_path.clone(),
-                    ".daikoku_data".to_string(),
-                    ".metadata".to_string(),
-                );
-                let mut data = new_custom_ini_file();
-                let data = Ini::read(&mut data, daikoku_data_file.content).map_err(|_err| {
-                    DaikokuCliError::FileSystem(format!(
-                        "unable to read daikoku_data file in {:#?}",
-                        daikoku_data_path
-                    ))
-                })?;
-                let mut default_section: HashMap<String, String> = HashMap::new();
-
-                data.get("default").map(|values| {
-                    values.iter().for_each(|value| {
-                        default_section.insert(
-                            value.0.to_string(),
-                            value.1.clone().unwrap_or("".to_string()),
-                        );
-                    })
-                });
-
-                daikoku_data = Some(default_section);
-            } else {
-                if let Some(extension) = entry.clone().path().extension() {
-                    let file_path = entry.clone().into_path().clone();
-                    let mut new_file = read_file(
-                        file_path.clone(),
-                        f_name.clone(),
-                        extension.to_string_lossy().into_owned(),
-                    );
-
-                    let out_daikoku_data = daikoku_data.clone();
-
-                    new_file.daikoku_data = match out_daikoku_data {
-                        Some(data) => {
-                            let mut new_file_metadata = data.clone();
-                            let identifier = new_file_metadata.get("id");
-
-                            let custom_file_path = file_path.clone();
-                            let file_identifier = custom_file_path
-                                .iter()
-                                .rev()
-                                .nth(1)
-                                .unwrap()
-                                .to_str()
-                                .unwrap();
-
-                            new_file_metadata.insert(
-                                "id".to_string(),
-                                identifier
-                                    .map(|id| id.to_owned() + file_identifier)
-                                    .unwrap_or(file_identifier.to_string())
-                                    .to_string(),
-                            );
-                            Some(new_file_metadata)
-                        }
-                        None => out_daikoku_data,
-                    };
-
-                    pages.push(new_file);
+/// walks through a folder, without entering the ignored folders
+fn walk<'a>(
+    path: &Path,
+    ignore: &'a DaikokuIgnore,
+) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|entry| !ignore.is_ignored(entry.path(), entry.file_type().is_dir()))
+}
+
+pub(crate) fn read_sources_and_daikoku_metadata(manifest: &ProjectManifest) -> Sources {
+    let mut sources = read_contents(manifest);
+
+    let mut apis_data: HashMap<String, Option<HashMap<String, String>>> = HashMap::new();
+
+    for page in sources.pages.iter_mut() {
+        if !page.path().starts_with("/") {
+            page.metadata
+                .insert("_path".to_string(), format!("/{}", page.path()).into());
+        }
+
+        let Some(file_path) = page.file_path.clone() else {
+            continue;
+        };
+
+        let api = manifest
+            .cms_path(&file_path)
+            .filter(|cms_path| cms_path.starts_with(SourceFolder::Apis.name()))
+            .and_then(|cms_path| {
+                cms_path
+                    .iter()
+                    .nth(1)
+                    .map(|api| api.to_string_lossy().into_owned())
+            });
+
+        let Some(api) = api else {
+            if let Some(id) = page.metadata_text("id") {
+                page.daikoku_data = Some(HashMap::from([("id".to_string(), id)]));
+            }
+            continue;
+        };
+
+        let data = apis_data.entry(api.clone()).or_insert_with(|| {
+            let data_path = manifest
+                .folder(SourceFolder::Apis)
+                .join(&api)
+                .join(".daikoku_data");
+
+            if !data_path.exists() {
+                return None;
+            }
+
+            match read_daikoku_data(&data_path) {
+                Ok(data) => Some(data),
+                Err(diagnostic) => {
+                    sources.diagnostics.push(diagnostic);
+                    None
                 }
             }
-        }
+        });
+
+        page.daikoku_data = data.clone().map(|mut data| {
+            let file_identifier = file_path
+                .iter()
+                .rev()
+                .nth(1)
+                .map(|identifier| identifier.to_string_lossy().into_owned())
+                .unwrap_or_default();
+
+            let identifier = data
+                .get("id")
+                .map(|id| id.to_owned() + &file_identifier)
+                .unwrap_or(file_identifier);
+
+            data.insert("id".to_string(), identifier);
+            data
+        });
     }
-    Ok(pages)
+
+    sources
 }
 
-fn read_file(file_path: PathBuf, file_name: String, extension: String) -> CmsFile {
-    let content = fs::read_to_string(&file_path).unwrap();
+fn read_daikoku_data(path: &Path) -> Result<HashMap<String, String>, Diagnostic> {
+    let content = fs::read_to_string(path).map_err(|err| Diagnostic::new(path, err))?;
 
-    let formatted = &file_path.as_os_str().to_str().unwrap().replace("\\", "/");
+    let mut data = new_custom_ini_file();
+    let data = Ini::read(&mut data, content).map_err(|err| Diagnostic::new(path, err))?;
 
-    let parts = &formatted
-        .split("src/")
-        .last()
-        .unwrap()
-        .split("/")
-        .collect::<Vec<&str>>();
+    let mut default_section: HashMap<String, String> = HashMap::new();
 
-    let mut formatted_path = parts[0..parts.len()]
-        .join("/")
+    data.get("default").map(|values| {
+        values.iter().for_each(|value| {
+            default_section.insert(
+                value.0.to_string(),
+                value.1.clone().unwrap_or("".to_string()),
+            );
+        })
+    });
+
+    Ok(default_section)
+}
+
+/// path of a file on Daikoku, computed from its path in the CMS
+fn page_route(cms_path: &Path, file_name: &str) -> String {
+    let mut formatted_path = cms_path
+        .to_string_lossy()
+        .replace("\\", "/")
         .replace("/pages", "/")
         .replace("pages/", "/")
         .replace("/page.html", "")
@@ -248,48 +385,60 @@ fn read_file(file_path: PathBuf, file_name: String, extension: String) -> CmsFil
         formatted_path = "/".to_string();
     }
 
-    formatted_path = formatted_path.replace(".html", "");
-
-    if content.contains("---") {
-        let parts = content.split("---");
-
-        let mut metadata: HashMap<String, String> =
-            serde_yaml::from_str(&parts.clone().nth(0).unwrap()).unwrap_or(HashMap::new());
-        let content = parts.into_iter().nth(1).unwrap();
-
-        metadata.insert("_path".to_string(), formatted_path.to_string());
-
-        metadata.insert(
-            "_content_type".to_string(),
-            SourceExtension::from_str(&extension)
-                .unwrap()
-                .content_type(),
-        );
-
-        metadata.insert("_name".to_string(), file_name.clone());
-        metadata.insert("from".to_string(), "cli".to_string());
+    formatted_path.replace(".html", "")
+}
 
-        CmsFile {
-            content: content.to_string(),
-            name: file_name,
-            metadata,
-            daikoku_data: None,
-        }
-    } else {
-        let mut metadata: HashMap<String, String> = HashMap::new();
-        metadata.insert("_path".to_string(), formatted_path.to_string());
-
-        metadata.insert(
-            "_content_type".to_string(),
-            SourceExtension::from_str(&extension)
-                .unwrap()
-                .content_type(),
-        );
-        metadata.insert("_name".to_string(), file_name.clone());
-        metadata.insert("from".to_string(), "cli".to_string());
-
-        CmsFile::new(file_path, file_name, metadata)
+fn read_file(
+    file_path: PathBuf,
+    file_name: String,
+    extension: String,
+    manifest: &ProjectManifest,
+) -> Result<CmsFile, Diagnostic> {
+    let content_type = SourceExtension::from_str(&extension)
+        .map_err(|_| {
+            Diagnostic::new(
+                &file_path,
+                format!(
+                    "unsupported .{} file, only html, css, js and json files can be sources",
+                    extension
+                ),
+            )
+        })?
+        .content_type();
+
+    let content = fs::read_to_string(&file_path).map_err(|err| Diagnostic::new(&file_path, err))?;
+
+    let cms_path = manifest
+        .cms_path(&file_path)
+        .ok_or_else(|| Diagnostic::new(&file_path, "the file is outside of the source folders"))?;
+
+    let formatted_path = page_route(&cms_path, &file_name);
+
+    let FrontMatter {
+        mut metadata,
+        body,
+        legacy,
+    } = front_matter::parse(&file_path, &content)?;
+
+    if legacy {
+        logger::info(format!(
+            "<yellow>Deprecated</> {} : the metadata has no opening --- line, add one at the start of the file",
+            file_path.display()
+        ));
     }
+
+    metadata.insert("_path".to_string(), formatted_path.into());
+    metadata.insert("_content_type".to_string(), content_type.into());
+    metadata.insert("_name".to_string(), file_name.clone().into());
+    metadata.insert("from".to_string(), "cli".into());
+
+    Ok(CmsFile {
+        content: body,
+        name: file_name,
+        metadata,
+        daikoku_data: None,
+        file_path: Some(file_path),
+    })
 }
 
 #[derive(Debug, PartialEq)]
@@ -319,6 +468,13 @@ impl ToString for SourceExtension {
     }
 }
 
+impl SourceExtension {
+    /// extension of a page stored in Daikoku, pages with another content type are written as html
+    pub(crate) fn from_content_type(content_type: &str) -> SourceExtension {
+        SourceExtension::from_str(content_type).unwrap_or(SourceExtension::HTML)
+    }
+}
+
 impl FromStr for SourceExtension {
     type Err = ();
 
@@ -333,7 +489,7 @@ impl FromStr for SourceExtension {
             "text/javascript" => Ok(SourceExtension::Javascript),
             "json" => Ok(SourceExtension::JSON),
             "application/json" => Ok(SourceExtension::JSON),
-            _ => Ok(SourceExtension::HTML),
+            _ => Err(()),
         }
     }
 }
@@ -0,0 +1,407 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use serde_yaml::Value;
+
+use crate::logging::error::{DaikokuCliError, DaikokuResult};
+
+use super::folder::Diagnostic;
+
+const DELIMITER: &str = "---";
+
+/// metadata read by Daikoku as booleans
+const BOOLEAN_METADATA: [&str; 3] = ["_authenticated", "_visible", "_exact"];
+
+/// metadata always written by older versions of the CLI, before the opening delimiter was
+/// required
+const LEGACY_KEYS: [&str; 4] = ["id", "_authenticated", "_visible", "_exact"];
+
+/// top-level `key: value` line, as written by older versions of the CLI
+static LEGACY_KEY_LINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([\w.-]+):(?:\s|$)").unwrap());
+
+/// `key: |` or `key: >` line, followed by the indented lines of a multi-line value
+static BLOCK_SCALAR: Lazy<Regex> = Lazy::new(|| Regex::new(r":\s*[|>][+-]?\d*\s*$").unwrap());
+
+/// Metadata of a page, sent to Daikoku with the YAML types of its values
+pub(crate) type Metadata = HashMap<String, JsonValue>;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct FrontMatter {
+    pub(crate) metadata: Metadata,
+    pub(crate) body: String,
+    /// the metadata was read without opening delimiter
+    pub(crate) legacy: bool,
+}
+
+fn is_delimiter(line: &str) -> bool {
+    line.trim_end() == DELIMITER
+}
+
+/// Splits a source file into its metadata and its body.
+///
+/// The metadata is a YAML mapping placed at the start of the file between two `---` lines.
+/// Files written by older versions of the CLI, without the opening delimiter, are still read
+/// when they have the exact layout written by the CLI, see `legacy_metadata_end`.
+pub(crate) fn parse(path: &Path, content: &str) -> Result<FrontMatter, Diagnostic> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+
+    let (yaml_start, yaml_end) = match lines.first() {
+        Some(first_line) if is_delimiter(first_line) => {
+            match lines.iter().skip(1).position(|line| is_delimiter(line)) {
+                Some(position) => (1, position + 1),
+                None => {
+                    return Err(front_matter_error(
+                        path,
+                        1,
+                        "the metadata block is never closed by a --- line",
+                    ))
+                }
+            }
+        }
+        _ => match legacy_metadata_end(&lines) {
+            Some(position) => (0, position),
+            None => {
+                return Ok(FrontMatter {
+                    metadata: HashMap::new(),
+                    body: content.to_string(),
+                    legacy: false,
+                })
+            }
+        },
+    };
+
+    let yaml = lines[yaml_start..yaml_end].concat();
+    let body = lines[yaml_end + 1..].concat();
+
+    Ok(FrontMatter {
+        metadata: parse_metadata(path, &yaml, yaml_start)?,
+        body,
+        legacy: yaml_start == 0,
+    })
+}
+
+/// Position of the `---` line closing the metadata of a file written by an older version of the
+/// CLI : top-level `key: value` lines, with at least one of the keys it always wrote, optionally
+/// followed by an empty line. Any other file is read as a page without metadata.
+fn legacy_metadata_end(lines: &[&str]) -> Option<usize> {
+    let position = lines.iter().position(|line| is_delimiter(line))?;
+
+    let block = match lines[..position].split_last() {
+        Some((last, block)) if last.trim().is_empty() => block,
+        _ => &lines[..position],
+    };
+
+    let mut keys = vec![];
+    let mut in_block_scalar = false;
+
+    for line in block {
+        if let Some(captures) = LEGACY_KEY_LINE.captures(line) {
+            keys.push(captures.get(1)?.as_str());
+            in_block_scalar = BLOCK_SCALAR.is_match(line.trim_end());
+        } else if !in_block_scalar
+            || !(line.trim().is_empty() || line.starts_with(char::is_whitespace))
+        {
+            return None;
+        }
+    }
+
+    keys.iter()
+        .any(|key| LEGACY_KEYS.contains(key))
+        .then_some(position)
+}
+
+fn parse_metadata(path: &Path, yaml: &str, first_line: usize) -> Result<Metadata, Diagnostic> {
+    if yaml.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let value: Value = serde_yaml::from_str(yaml).map_err(|err| {
+        let line = err
+            .location()
+            .map(|location| location.line() + first_line)
+            .unwrap_or(first_line + 1);
+        front_matter_error(path, line, &err.to_string())
+    })?;
+
+    let mapping = match value {
+        Value::Mapping(mapping) => mapping,
+        Value::Null => return Ok(HashMap::new()),
+        _ => {
+            return Err(front_matter_error(
+                path,
+                first_line + 1,
+                "the metadata must be a list of key: value",
+            ))
+        }
+    };
+
+    let mut metadata = HashMap::new();
+
+    for (key, value) in mapping {
+        let line = line_of_key(yaml, &key)
+            .map(|line| line + first_line)
+            .unwrap_or(first_line + 1);
+
+        let key = match key {
+            Value::String(key) => key,
+            key => scalar_to_string(&key).ok_or(front_matter_error(
+                path,
+                line,
+                "metadata keys must be strings",
+            ))?,
+        };
+
+        // Daikoku reads the page flags as strings
+        let value = if BOOLEAN_METADATA.contains(&key.as_str()) {
+            match value {
+                Value::Bool(value) => JsonValue::String(value.to_string()),
+                Value::String(value) if value == "true" || value == "false" => {
+                    JsonValue::String(value)
+                }
+                _ => {
+                    return Err(front_matter_error(
+                        path,
+                        line,
+                        &format!("{} must be true or false", key),
+                    ))
+                }
+            }
+        } else {
+            to_json(value).map_err(|reason| {
+                front_matter_error(path, line, &format!("{} : {}", key, reason))
+            })?
+        };
+
+        metadata.insert(key, value);
+    }
+
+    Ok(metadata)
+}
+
+fn to_json(value: Value) -> Result<JsonValue, String> {
+    match value {
+        Value::Null => Ok(JsonValue::Null),
+        Value::Bool(value) => Ok(JsonValue::Bool(value)),
+        Value::Number(value) => serde_json::to_value(value).map_err(|err| err.to_string()),
+        Value::String(value) => Ok(JsonValue::String(value)),
+        Value::Sequence(values) => values
+            .into_iter()
+            .map(to_json)
+            .collect::<Result<Vec<_>, _>>()
+            .map(JsonValue::Array),
+        Value::Mapping(mapping) => mapping
+            .into_iter()
+            .map(|(key, value)| {
+                let key = scalar_to_string(&key).ok_or("keys must be strings".to_string())?;
+                Ok((key, to_json(value)?))
+            })
+            .collect::<Result<serde_json::Map<_, _>, String>>()
+            .map(JsonValue::Object),
+        Value::Tagged(tagged) => to_json(tagged.value),
+    }
+}
+
+/// Value of a metadata as text : strings as they are, other values as JSON
+pub(crate) fn metadata_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "".to_string(),
+        JsonValue::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some("".to_string()),
+        Value::Bool(value) => Some(value.to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        Value::String(value) => Some(value.clone()),
+        Value::Tagged(tagged) => scalar_to_string(&tagged.value),
+        Value::Sequence(_) | Value::Mapping(_) => None,
+    }
+}
+
+/// 1-based line of a top-level key in the metadata block
+fn line_of_key(yaml: &str, key: &Value) -> Option<usize> {
+    let key = scalar_to_string(key)?;
+
+    yaml.lines()
+        .position(|line| line.starts_with(&key) && line[key.len()..].trim_start().starts_with(':'))
+        .map(|position| position + 1)
+}
+
+fn front_matter_error(path: &Path, line: usize, reason: &str) -> Diagnostic {
+    Diagnostic::at_line(path, line, format!("invalid metadata, {}", reason))
+}
+
+/// Writes the metadata at the start of the body, sorted by key
+pub(crate) fn format(metadata: &Metadata, body: &str) -> DaikokuResult<String> {
+    let sorted: BTreeMap<&String, JsonValue> = metadata
+        .iter()
+        .map(|(key, value)| {
+            let value = match value.as_str() {
+                Some("true") => JsonValue::Bool(true),
+                Some("false") => JsonValue::Bool(false),
+                _ => value.clone(),
+            };
+            (key, value)
+        })
+        .collect();
+
+    let yaml = serde_yaml::to_string(&sorted)
+        .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?;
+
+    Ok(format!("{}\n{}{}\n{}", DELIMITER, yaml, DELIMITER, body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn parsed(content: &str) -> FrontMatter {
+        parse(Path::new("page.html"), content).unwrap()
+    }
+
+    fn error(content: &str) -> Diagnostic {
+        parse(Path::new("page.html"), content).unwrap_err()
+    }
+
+    #[test]
+    fn metadata_between_delimiters() {
+        let page = parsed("---\ntitle: Home\n---\n<h1>home</h1>\n---\nfooter");
+
+        assert_eq!(
+            page.metadata,
+            HashMap::from([("title".to_string(), json!("Home"))])
+        );
+        assert_eq!(page.body, "<h1>home</h1>\n---\nfooter");
+        assert!(!page.legacy);
+    }
+
+    #[test]
+    fn page_without_metadata() {
+        let page = parsed("<h1>home</h1>");
+
+        assert!(page.metadata.is_empty());
+        assert_eq!(page.body, "<h1>home</h1>");
+
+        let page = parsed("\u{feff}---\n---\nbody");
+
+        assert!(page.metadata.is_empty());
+        assert_eq!(page.body, "body");
+    }
+
+    #[test]
+    fn unclosed_metadata() {
+        let diagnostic = error("---\ntitle: Home\n<h1>home</h1>");
+
+        assert_eq!(diagnostic.line, Some(1));
+        assert!(diagnostic.reason.contains("never closed"));
+    }
+
+    #[test]
+    fn legacy_layout() {
+        let page = parsed("id: home\n_exact: true\n\n---\n<h1>home</h1>");
+
+        assert!(page.legacy);
+        assert_eq!(page.metadata["id"], json!("home"));
+        assert_eq!(page.metadata["_exact"], json!("true"));
+        assert_eq!(page.body, "<h1>home</h1>");
+
+        let page =
+            parsed("description: |\n  first line\n\n  second line\n_visible: false\n---\nbody");
+
+        assert!(page.legacy);
+        assert_eq!(
+            page.metadata["description"],
+            json!("first line\n\nsecond line\n")
+        );
+    }
+
+    #[test]
+    fn text_before_separator_is_body() {
+        for content in [
+            "Note: read this\n---\n<p>content</p>",
+            "title: Home\n---\n<p>content</p>",
+            "id: home\n<p>text</p>\n---\n<p>content</p>",
+        ] {
+            let page = parsed(content);
+
+            assert!(page.metadata.is_empty(), "{:?} read as metadata", content);
+            assert_eq!(page.body, content);
+            assert!(!page.legacy);
+        }
+    }
+
+    #[test]
+    fn typed_values() {
+        let page = parsed(
+            "---\n_authenticated: true\n_visible: \"false\"\n_exact: false\norder: 2\ndraft: true\ntags:\n  - a\n  - b\nempty:\n---\n",
+        );
+
+        assert_eq!(page.metadata["_authenticated"], json!("true"));
+        assert_eq!(page.metadata["_visible"], json!("false"));
+        assert_eq!(page.metadata["_exact"], json!("false"));
+        assert_eq!(page.metadata["order"], json!(2));
+        assert_eq!(page.metadata["draft"], json!(true));
+        assert_eq!(page.metadata["tags"], json!(["a", "b"]));
+        assert_eq!(page.metadata["empty"], JsonValue::Null);
+    }
+
+    #[test]
+    fn error_lines() {
+        let diagnostic = error("---\ntitle: Home\n_exact: maybe\n---\n");
+
+        assert_eq!(diagnostic.line, Some(3));
+        assert_eq!(
+            diagnostic.reason,
+            "invalid metadata, _exact must be true or false"
+        );
+
+        let diagnostic = error("---\ntitle: Home\nsubtitle: a: b\n---\n");
+
+        assert_eq!(diagnostic.line, Some(3));
+
+        let diagnostic = error("---\n- a\n- b\n---\n");
+
+        assert_eq!(diagnostic.line, Some(2));
+        assert!(diagnostic.reason.contains("list of key: value"));
+
+        let diagnostic = error("id: home\n_visible: sometimes\n---\nbody");
+
+        assert_eq!(diagnostic.line, Some(2));
+    }
+
+    #[test]
+    fn metadata_as_text() {
+        assert_eq!(metadata_text(&JsonValue::Null), "");
+        assert_eq!(metadata_text(&json!("text")), "text");
+        assert_eq!(metadata_text(&json!(2)), "2");
+        assert_eq!(metadata_text(&json!(["a"])), "[\"a\"]");
+    }
+
+    #[test]
+    fn formatted_metadata() {
+        let metadata = HashMap::from([
+            ("_exact".to_string(), json!("true")),
+            ("title".to_string(), json!("Home")),
+            ("order".to_string(), json!(2)),
+        ]);
+
+        let content = format(&metadata, "<h1>home</h1>").unwrap();
+
+        assert_eq!(
+            content,
+            "---\n_exact: true\norder: 2\ntitle: Home\n---\n<h1>home</h1>"
+        );
+        assert_eq!(parsed(&content).metadata, metadata);
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use std::{collections::HashMap, fs, path::PathBuf, process::Command};
+
+use crate::{
+    helpers::map_error_to_filesystem_error,
+    logging::error::{DaikokuCliError, DaikokuResult},
+};
+
+use super::folder::CmsFile;
+
+/// Pages sent by a push to an environment, stored in .daikoku/history/<id>.json
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct PushSnapshot {
+    pub(crate) id: String,
+    pub(crate) environment: String,
+    /// milliseconds since the epoch
+    pub(crate) timestamp: i64,
+    /// commit checked out in the project when the pages were pushed
+    pub(crate) commit: Option<String>,
+    pub(crate) pages: Vec<CmsFile>,
+    /// paths of the remote pages deleted by `push --prune`, after the pages were sent
+    #[serde(default)]
+    pub(crate) pruned: Vec<String>,
+}
+
+fn history_path(project_path: &str) -> PathBuf {
+    PathBuf::from(project_path).join(".daikoku").join("history")
+}
+
+impl PushSnapshot {
+    pub(crate) fn new(project_path: &str, environment: &str, pages: &[CmsFile]) -> PushSnapshot {
+        let now = chrono::Utc::now();
+
+        PushSnapshot {
+            id: now.format("%Y%m%d-%H%M%S-%3f").to_string(),
+            environment: environment.to_string(),
+            timestamp: now.timestamp_millis(),
+            commit: current_commit(project_path),
+            pages: pages.to_vec(),
+            pruned: vec![],
+        }
+    }
+
+    pub(crate) fn save(&self, project_path: &str) -> DaikokuResult<()> {
+        let path = history_path(project_path);
+
+        fs::create_dir_all(&path)
+            .map_err(|err| map_error_to_filesystem_error(err, "PushSnapshot"))?;
+
+        let content = serde_json::to_string(self)
+            .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?;
+
+        fs::write(path.join(format!("{}.json", self.id)), content)
+            .map_err(|err| map_error_to_filesystem_error(err, "PushSnapshot"))
+    }
+
+    pub(crate) fn load(project_path: &str, id: &str) -> DaikokuResult<PushSnapshot> {
+        let path = history_path(project_path).join(format!("{}.json", id));
+
+        if !path.exists() {
+            return Err(DaikokuCliError::FileSystem(format!(
+                "snapshot {} not found, see daikoku history",
+                id
+            )));
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| map_error_to_filesystem_error(err, "PushSnapshot"))?;
+
+        serde_json::from_str(&content).map_err(|err| {
+            DaikokuCliError::ParsingError(format!("failed to read snapshot {:?} : {}", path, err))
+        })
+    }
+
+    /// every snapshot of the project, from the oldest to the most recent
+    pub(crate) fn list(project_path: &str) -> DaikokuResult<Vec<PushSnapshot>> {
+        let path = history_path(project_path);
+
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut ids: Vec<String> = fs::read_dir(&path)
+            .map_err(|err| map_error_to_filesystem_error(err, "PushSnapshot"))?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.strip_suffix(".json"))
+                    .map(|id| id.to_string())
+            })
+            .collect();
+        ids.sort();
+
+        ids.iter()
+            .map(|id| PushSnapshot::load(project_path, id))
+            .collect()
+    }
+
+    /// Pages of the environment as they were right after this push : the pages of the previous
+    /// snapshots, replaced by the ones sent by this push, without the pruned ones
+    pub(crate) fn pages_at(&self, project_path: &str) -> DaikokuResult<Vec<CmsFile>> {
+        let mut pages: HashMap<String, CmsFile> = HashMap::new();
+
+        PushSnapshot::list(project_path)?
+            .into_iter()
+            .filter(|snapshot| snapshot.environment == self.environment && snapshot.id <= self.id)
+            .for_each(|snapshot| {
+                snapshot.pages.into_iter().for_each(|page| {
+                    pages.insert(page.id().unwrap_or(page.path()), page);
+                });
+                pages.retain(|_, page| !snapshot.pruned.contains(&page.path()));
+            });
+
+        let mut pages: Vec<CmsFile> = pages.into_values().collect();
+        pages.sort_by_key(|page| page.path());
+
+        Ok(pages)
+    }
+}
+
+fn current_commit(project_path: &str) -> Option<String> {
+    Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(project_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .filter(|commit| !commit.is_empty())
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::PathBuf,
+};
+
+use crate::{
+    helpers::map_error_to_filesystem_error,
+    logging::error::{DaikokuCliError, DaikokuResult},
+};
+
+use super::{folder::CmsFile, front_matter};
+
+/// Content hashes of the files sent during the last successful push to an environment, and
+/// published dates of the pages received by the last pull, stored in
+/// .daikoku/manifests/<environment>.json
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct PushManifest {
+    pub(crate) pages: HashMap<String, String>,
+    #[serde(default)]
+    pub(crate) published_dates: HashMap<String, u64>,
+}
+
+fn manifest_path(project_path: &str, environment: &str) -> PathBuf {
+    PathBuf::from(project_path)
+        .join(".daikoku")
+        .join("manifests")
+        .join(format!("{}.json", environment))
+}
+
+impl PushManifest {
+    pub(crate) fn load(project_path: &str, environment: &str) -> DaikokuResult<PushManifest> {
+        let path = manifest_path(project_path, environment);
+
+        if !path.exists() {
+            return Ok(PushManifest::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|err| map_error_to_filesystem_error(err, "PushManifest"))?;
+
+        serde_json::from_str(&content).map_err(|err| {
+            DaikokuCliError::ParsingError(format!(
+                "failed to read push manifest {:?} : {}",
+                path, err
+            ))
+        })
+    }
+
+    pub(crate) fn save(&self, project_path: &str, environment: &str) -> DaikokuResult<()> {
+        let path = manifest_path(project_path, environment);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|err| map_error_to_filesystem_error(err, "PushManifest"))?;
+        }
+
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?;
+
+        fs::write(path, content).map_err(|err| map_error_to_filesystem_error(err, "PushManifest"))
+    }
+
+    pub(crate) fn has_changed(&self, file: &CmsFile) -> bool {
+        self.pages.get(&file.path()) != Some(&hash(file))
+    }
+
+    pub(crate) fn record(&mut self, files: &[CmsFile]) {
+        files.iter().for_each(|file| {
+            self.pages.insert(file.path(), hash(file));
+        });
+    }
+
+    pub(crate) fn published_date(&self, path: &str) -> Option<u64> {
+        self.published_dates.get(path).copied()
+    }
+
+    /// keeps the published date of a pulled page, to detect later changes made on Daikoku
+    pub(crate) fn record_published_date(&mut self, path: String, date: Option<u64>) {
+        match date {
+            Some(date) => self.published_dates.insert(path, date),
+            None => self.published_dates.remove(&path),
+        };
+    }
+
+    /// removes a page deleted from Daikoku, so that it is pushed again if it is recreated
+    pub(crate) fn forget(&mut self, path: &str) {
+        self.pages.remove(path);
+        self.published_dates.remove(path);
+    }
+}
+
+/// Hash of everything sent to Daikoku for a file : content, metadata and daikoku data
+pub(crate) fn hash(file: &CmsFile) -> String {
+    let metadata: BTreeMap<&String, String> = file
+        .metadata
+        .iter()
+        .map(|(key, value)| (key, front_matter::metadata_text(value)))
+        .collect();
+    let daikoku_data: Option<BTreeMap<&String, &String>> =
+        file.daikoku_data.as_ref().map(|data| data.iter().collect());
+
+    let mut hasher = Sha256::new();
+    hasher.update(file.name.as_bytes());
+    hasher.update(file.content.as_bytes());
+    hasher.update(
+        serde_json::to_string(&metadata)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+    hasher.update(
+        serde_json::to_string(&daikoku_data)
+            .unwrap_or_default()
+            .as_bytes(),
+    );
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> CmsFile {
+        CmsFile {
+            name: "page.html".to_string(),
+            content: content.to_string(),
+            metadata: HashMap::from([("_path".to_string(), path.into())]),
+            ..CmsFile::default()
+        }
+    }
+
+    fn project() -> String {
+        std::env::temp_dir()
+            .join(format!("daikoku-manifest-{}", uuid::Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn changed_files() {
+        let mut manifest = PushManifest::default();
+        let page = file("/home", "<h1>home</h1>");
+
+        assert!(manifest.has_changed(&page));
+
+        manifest.record(&[page.clone()]);
+
+        assert!(!manifest.has_changed(&page));
+        assert!(manifest.has_changed(&file("/home", "<h1>updated</h1>")));
+        assert!(manifest.has_changed(&file("/other", "<h1>home</h1>")));
+
+        let mut exact = page.clone();
+        exact.metadata.insert("_exact".to_string(), "true".into());
+
+        assert!(manifest.has_changed(&exact));
+
+        manifest.forget("/home");
+
+        assert!(manifest.has_changed(&page));
+    }
+
+    #[test]
+    fn manifest_per_environment() {
+        let project = project();
+
+        let mut manifest = PushManifest::default();
+        manifest.record(&[file("/home", "home")]);
+        manifest.save(&project, "prod").unwrap();
+
+        assert!(PathBuf::from(&project)
+            .join(".daikoku")
+            .join("manifests")
+            .join("prod.json")
+            .exists());
+        assert_eq!(
+            PushManifest::load(&project, "prod").unwrap().pages,
+            manifest.pages
+        );
+        assert!(PushManifest::load(&project, "staging")
+            .unwrap()
+            .pages
+            .is_empty());
+    }
+
+    #[test]
+    fn corrupted_manifest() {
+        let project = project();
+        let path = manifest_path(&project, "prod");
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ not json").unwrap();
+
+        assert!(matches!(
+            PushManifest::load(&project, "prod"),
+            Err(DaikokuCliError::ParsingError(_))
+        ));
+    }
+}
@@ -1 +1,6 @@
-pub mod folder;
\ No newline at end of file
+pub mod daikokuignore;
+pub mod folder;
+pub mod front_matter;
+pub mod history;
+pub mod manifest;
+pub mod project_manifest;
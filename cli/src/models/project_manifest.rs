@@ -0,0 +1,227 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::logging::error::{DaikokuCliError, DaikokuResult};
+
+use super::daikokuignore::DaikokuIgnore;
+
+pub(crate) const MANIFEST_FILE: &str = "daikoku.toml";
+
+/// Kind of sources. Each kind is published under its own name, except the pages which are served
+/// from the root of the site
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SourceFolder {
+    Pages,
+    Blocks,
+    Styles,
+    Scripts,
+    Data,
+    Mails,
+    Apis,
+    Documentations,
+    Customization,
+}
+
+impl SourceFolder {
+    pub(crate) const ALL: [SourceFolder; 9] = [
+        SourceFolder::Pages,
+        SourceFolder::Blocks,
+        SourceFolder::Styles,
+        SourceFolder::Scripts,
+        SourceFolder::Data,
+        SourceFolder::Mails,
+        SourceFolder::Apis,
+        SourceFolder::Documentations,
+        SourceFolder::Customization,
+    ];
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            SourceFolder::Pages => "pages",
+            SourceFolder::Blocks => "blocks",
+            SourceFolder::Styles => "styles",
+            SourceFolder::Scripts => "scripts",
+            SourceFolder::Data => "data",
+            SourceFolder::Mails => "mails",
+            SourceFolder::Apis => "apis",
+            SourceFolder::Documentations => "documentations",
+            SourceFolder::Customization => "customization",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<SourceFolder> {
+        SourceFolder::ALL
+            .into_iter()
+            .find(|folder| folder.name() == name)
+    }
+}
+
+/// content of the daikoku.toml file, every value is optional
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ManifestFile {
+    /// folder of the assets, relative to the project
+    assets: Option<String>,
+    default_environment: Option<String>,
+    /// rules added to the .daikokuignore ones
+    ignore: Vec<String>,
+    sources: SourcesSection,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct SourcesSection {
+    /// folder of the sources, relative to the project
+    root: Option<String>,
+    // folders of each kind of sources, relative to the root
+    pages: Option<String>,
+    blocks: Option<String>,
+    styles: Option<String>,
+    scripts: Option<String>,
+    data: Option<String>,
+    mails: Option<String>,
+    apis: Option<String>,
+    documentations: Option<String>,
+    customization: Option<String>,
+}
+
+impl SourcesSection {
+    fn folder(&self, folder: SourceFolder) -> Option<&String> {
+        match folder {
+            SourceFolder::Pages => self.pages.as_ref(),
+            SourceFolder::Blocks => self.blocks.as_ref(),
+            SourceFolder::Styles => self.styles.as_ref(),
+            SourceFolder::Scripts => self.scripts.as_ref(),
+            SourceFolder::Data => self.data.as_ref(),
+            SourceFolder::Mails => self.mails.as_ref(),
+            SourceFolder::Apis => self.apis.as_ref(),
+            SourceFolder::Documentations => self.documentations.as_ref(),
+            SourceFolder::Customization => self.customization.as_ref(),
+        }
+    }
+}
+
+/// Layout of a project, read from its daikoku.toml file. Projects without this file use the
+/// layout created by `daikoku cms init`
+#[derive(Debug, Clone)]
+pub(crate) struct ProjectManifest {
+    sources: PathBuf,
+    folders: Vec<(SourceFolder, PathBuf)>,
+    assets: PathBuf,
+    pub(crate) default_environment: Option<String>,
+    pub(crate) ignore: DaikokuIgnore,
+}
+
+impl ProjectManifest {
+    pub(crate) fn load(project_path: &Path) -> DaikokuResult<ProjectManifest> {
+        let manifest_path = project_path.join(MANIFEST_FILE);
+
+        let file: ManifestFile = if manifest_path.exists() {
+            let content = fs::read_to_string(&manifest_path).map_err(|err| {
+                DaikokuCliError::Configuration(format!(
+                    "unable to read {} : {}",
+                    manifest_path.display(),
+                    err
+                ))
+            })?;
+
+            toml::from_str(&content).map_err(|err| {
+                DaikokuCliError::Configuration(format!(
+                    "invalid {} : {}",
+                    manifest_path.display(),
+                    err.to_string().trim()
+                ))
+            })?
+        } else {
+            ManifestFile::default()
+        };
+
+        let sources = project_path.join(file.sources.root.as_deref().unwrap_or("src"));
+
+        let folders = SourceFolder::ALL
+            .into_iter()
+            .map(|folder| {
+                let path = file
+                    .sources
+                    .folder(folder)
+                    .map(|path| path.as_str())
+                    .unwrap_or(folder.name());
+                (folder, sources.join(path))
+            })
+            .collect();
+
+        Ok(ProjectManifest {
+            sources,
+            folders,
+            assets: project_path.join(file.assets.as_deref().unwrap_or("assets")),
+            default_environment: file.default_environment,
+            ignore: DaikokuIgnore::load(project_path, &file.ignore)?,
+        })
+    }
+
+    pub(crate) fn folder(&self, folder: SourceFolder) -> PathBuf {
+        self.folders
+            .iter()
+            .find(|(source_folder, _)| *source_folder == folder)
+            .map(|(_, path)| path.clone())
+            .unwrap_or(self.sources.join(folder.name()))
+    }
+
+    pub(crate) fn assets(&self) -> &Path {
+        &self.assets
+    }
+
+    /// folders to read or to watch : the source root and the folders located outside of it
+    pub(crate) fn source_roots(&self) -> Vec<PathBuf> {
+        let mut roots = vec![self.sources.clone()];
+
+        for (_, path) in &self.folders {
+            if !roots.iter().any(|root| path.starts_with(root)) {
+                roots.retain(|root| !root.starts_with(path));
+                roots.push(path.clone());
+            }
+        }
+
+        roots
+    }
+
+    /// Path of a file in the CMS, starting with the kind of the file, like `styles/main.css`.
+    /// Files outside of the kind folders keep their path relative to the source root
+    pub(crate) fn cms_path(&self, file_path: &Path) -> Option<PathBuf> {
+        self.folders
+            .iter()
+            .filter(|(_, path)| file_path.starts_with(path))
+            .max_by_key(|(_, path)| path.components().count())
+            .and_then(|(folder, path)| {
+                file_path
+                    .strip_prefix(path)
+                    .ok()
+                    .map(|relative_path| PathBuf::from(folder.name()).join(relative_path))
+            })
+            .or_else(|| {
+                file_path
+                    .strip_prefix(&self.sources)
+                    .ok()
+                    .map(|relative_path| relative_path.to_path_buf())
+            })
+    }
+
+    /// Location on disk of a file of the CMS, the reverse of `cms_path`
+    pub(crate) fn local_path(&self, cms_path: &Path) -> PathBuf {
+        let mut components = cms_path.components();
+
+        let folder = components
+            .next()
+            .and_then(|component| component.as_os_str().to_str())
+            .and_then(SourceFolder::from_name);
+
+        match folder {
+            Some(folder) => self.folder(folder).join(components.as_path()),
+            None => self.sources.join(cms_path),
+        }
+    }
+}
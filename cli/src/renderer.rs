@@ -0,0 +1,254 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use handlebars::{
+    BlockContext, Context, Handlebars, Helper, HelperResult, JsonRender, JsonTruthy, Output,
+    RenderContext, RenderError, RenderErrorReason, Renderable,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    helpers::map_error_to_filesystem_error,
+    logging::error::{DaikokuCliError, DaikokuResult},
+    models::folder::CmsFile,
+};
+
+/// file of the project root holding the stub data used by the offline renderer
+pub(crate) const DEFAULT_FIXTURES_FILE: &str = "fixtures.json";
+
+/// Reads the stub data of the offline renderer. Top-level keys are exposed to the templates
+/// as variables, and are also used as the result of the Daikoku helpers ({{#daikoku-apis}}, ...)
+pub(crate) fn read_fixtures(project_path: &str, file: Option<String>) -> DaikokuResult<Value> {
+    let path = file
+        .map(PathBuf::from)
+        .unwrap_or(PathBuf::from(project_path).join(DEFAULT_FIXTURES_FILE));
+
+    if !path.exists() {
+        return Ok(Value::Object(Map::new()));
+    }
+
+    let content =
+        fs::read_to_string(&path).map_err(|err| map_error_to_filesystem_error(err, "fixtures"))?;
+
+    let fixtures: Value = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+            .map_err(|err| DaikokuCliError::ParsingError(format!("{:?} : {}", path, err)))?,
+        _ => serde_json::from_str(&content)
+            .map_err(|err| DaikokuCliError::ParsingError(format!("{:?} : {}", path, err)))?,
+    };
+
+    if fixtures.is_object() {
+        Ok(fixtures)
+    } else {
+        Err(DaikokuCliError::ParsingError(format!(
+            "{:?} : fixtures must be an object",
+            path
+        )))
+    }
+}
+
+/// same normalization as the Daikoku renderer when a page is searched by its path
+fn clean_path(path: &str) -> String {
+    let out = path.replace("/_/", "/").replace(".html", "");
+
+    if out.starts_with('/') {
+        out
+    } else {
+        format!("/{}", out)
+    }
+}
+
+/// Renders locally the page at current_page, without calling Daikoku
+pub(crate) fn render(
+    content: &[CmsFile],
+    current_page: &str,
+    fields: &HashMap<String, String>,
+    fixtures: &Value,
+) -> DaikokuResult<String> {
+    let mut registry = Handlebars::new();
+
+    for file in content {
+        let path = clean_path(&file.path());
+
+        registry
+            .register_template_string(&path, &file.content)
+            .map_err(|err| {
+                DaikokuCliError::ParsingError(format!("failed to parse {} : {}", path, err))
+            })?;
+
+        if let Some(block) = path.strip_prefix("/blocks/") {
+            let _ = registry.register_partial(block, &file.content);
+        }
+    }
+
+    registry.register_helper("helperMissing", Box::new(stub_helper));
+    registry.register_helper("blockHelperMissing", Box::new(stub_helper));
+    registry.register_helper("daikoku-include-block", Box::new(include_block_helper));
+    registry.register_helper(
+        "daikoku-template-wrapper",
+        Box::new(template_wrapper_helper),
+    );
+    registry.register_helper("daikoku-asset-url", Box::new(asset_url_helper));
+    registry.register_helper("daikoku-page-url", Box::new(page_url_helper));
+
+    let mut data = fixtures.as_object().cloned().unwrap_or_default();
+
+    for (key, value) in fields {
+        if key != "email" {
+            data.insert(key.clone(), Value::String(value.clone()));
+        }
+    }
+
+    // like Daikoku, the body of a mail is rendered before being wrapped by the mail template
+    if let Some(email) = fields.get("email") {
+        let body = registry
+            .render_template(email, &data)
+            .map_err(|err| DaikokuCliError::ParsingError(err.to_string()))?;
+        data.insert("email".to_string(), Value::String(body));
+    }
+
+    let page = clean_path(current_page);
+
+    if !registry.has_template(&page) {
+        return Err(DaikokuCliError::FileSystem(format!(
+            "page {} not found",
+            page
+        )));
+    }
+
+    registry.render(&page, &data).map_err(|err| {
+        DaikokuCliError::ParsingError(format!("failed to render {} : {}", page, err))
+    })
+}
+
+fn first_param(h: &Helper, name: &'static str) -> Result<String, RenderError> {
+    h.param(0)
+        .map(|param| param.value().render())
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex(name, 0).into())
+}
+
+/// context of an included block : the current one and the hash of the helper
+fn block_context(h: &Helper, ctx: &Context) -> Result<Context, RenderError> {
+    let mut data = ctx.data().as_object().cloned().unwrap_or_default();
+
+    for (key, value) in h.hash() {
+        data.insert(key.to_string(), value.value().clone());
+    }
+
+    Context::wraps(data)
+}
+
+/// Daikoku helpers and missing variables are resolved from the fixtures
+fn stub_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = ctx.data().get(h.name()).cloned().unwrap_or(Value::Null);
+
+    match h.template() {
+        None => {
+            if !value.is_null() {
+                out.write(&value.render())?;
+            }
+            Ok(())
+        }
+        Some(template) => {
+            let items = match value {
+                Value::Array(items) => items,
+                value if value.is_truthy(false) => vec![value],
+                _ => vec![],
+            };
+
+            if items.is_empty() {
+                if let Some(inverse) = h.inverse() {
+                    inverse.render(r, ctx, rc, out)?;
+                }
+            }
+
+            for item in items {
+                let mut block = BlockContext::new();
+                block.set_base_value(item);
+                rc.push_block(block);
+                template.render(r, ctx, rc, out)?;
+                rc.pop_block();
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn include_block_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    _: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let id = clean_path(&first_param(h, "daikoku-include-block")?);
+
+    if r.has_template(&id) {
+        out.write(&r.render_with_context(&id, &block_context(h, ctx)?)?)?;
+    } else {
+        out.write(&format!("block '{}' not found", id))?;
+    }
+
+    Ok(())
+}
+
+fn template_wrapper_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let id = clean_path(&first_param(h, "daikoku-template-wrapper")?);
+
+    if !r.has_template(&id) {
+        out.write("wrapper component not found")?;
+        return Ok(());
+    }
+
+    let children = match h.template() {
+        Some(template) => template.renders(r, ctx, rc)?,
+        None => String::new(),
+    };
+
+    let mut context = block_context(h, ctx)?;
+    if let Some(data) = context.data_mut().as_object_mut() {
+        data.insert("children".to_string(), Value::String(children));
+    }
+
+    out.write(&r.render_with_context(&id, &context)?)?;
+
+    Ok(())
+}
+
+fn asset_url_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    _: &'reg Handlebars<'reg>,
+    _: &'rc Context,
+    _: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&format!(
+        "/tenant-assets/{}",
+        first_param(h, "daikoku-asset-url")?
+    ))?;
+    Ok(())
+}
+
+/// pages are identified by their path in the CLI
+fn page_url_helper<'reg, 'rc>(
+    h: &Helper<'rc>,
+    _: &'reg Handlebars<'reg>,
+    _: &'rc Context,
+    _: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&clean_path(&first_param(h, "daikoku-page-url")?))?;
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+static PARAM: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[(\w+)\]").unwrap());
+
+/// Segment of a page path
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    /// end of a non exact page, matching any number of segments
+    Wildcard,
+    /// [name] segment, matching any segment
+    Param(String),
+    Static(String),
+}
+
+impl Segment {
+    fn parse(segment: &str) -> Segment {
+        match PARAM.captures(segment) {
+            Some(captures) => Segment::Param(captures[1].to_string()),
+            None => Segment::Static(segment.to_string()),
+        }
+    }
+
+    /// matching priority : static > param > wildcard
+    fn rank(&self) -> u8 {
+        match self {
+            Segment::Wildcard => 0,
+            Segment::Param(_) => 1,
+            Segment::Static(_) => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Route {
+    pub(crate) path: String,
+    exact: bool,
+    segments: Vec<Segment>,
+}
+
+impl Route {
+    pub(crate) fn new(path: String, exact: bool) -> Route {
+        let mut segments: Vec<Segment> = path
+            .replace("/_/", "")
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(Segment::parse)
+            .collect();
+
+        if !exact {
+            segments.push(Segment::Wildcard);
+        }
+
+        Route {
+            path,
+            exact,
+            segments,
+        }
+    }
+
+    /// Matches the segments of a request like Daikoku does : a wildcard matches the rest of the
+    /// request, and a route longer than the request still matches, with the lowest priority
+    fn matches(&self, request: &[&str]) -> Option<Candidate<'_>> {
+        let mut params = BTreeMap::new();
+        let mut ranks = vec![];
+
+        for (index, value) in request.iter().enumerate() {
+            let segment = self.segments.get(index)?;
+
+            match segment {
+                Segment::Wildcard => {
+                    ranks.extend(request[index..].iter().map(|_| segment.rank()));
+                    return Some(Candidate {
+                        route: self,
+                        params,
+                        complete: true,
+                        ranks,
+                    });
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), value.to_string());
+                }
+                Segment::Static(static_segment) if static_segment == value => {}
+                Segment::Static(_) => return None,
+            }
+
+            ranks.push(segment.rank());
+        }
+
+        let remaining = &self.segments[request.len()..];
+
+        Some(Candidate {
+            route: self,
+            params,
+            complete: remaining.is_empty() || remaining == [Segment::Wildcard],
+            ranks,
+        })
+    }
+}
+
+struct Candidate<'a> {
+    route: &'a Route,
+    params: BTreeMap<String, String>,
+    complete: bool,
+    ranks: Vec<u8>,
+}
+
+impl Candidate<'_> {
+    /// complete matches first, then static > param > wildcard segment by segment,
+    /// and the path as the last resort to stay deterministic
+    fn priority(&self, other: &Candidate) -> Ordering {
+        self.complete
+            .cmp(&other.complete)
+            .then_with(|| self.ranks.cmp(&other.ranks))
+            .then_with(|| other.route.path.cmp(&self.route.path))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct RouteMatch {
+    pub(crate) path: String,
+    pub(crate) params: BTreeMap<String, String>,
+}
+
+/// Routing table of the CMS pages, following the server-side rules of Daikoku (CmsRouter) : a
+/// page is first searched by its path, then among the exact pages, then among the others
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub(crate) fn new(routes: Vec<Route>) -> Router {
+        Router { routes }
+    }
+
+    pub(crate) fn find(&self, path: &str) -> Option<RouteMatch> {
+        if let Some(route) = self.routes.iter().find(|route| route.path == path) {
+            return Some(RouteMatch {
+                path: route.path.clone(),
+                params: BTreeMap::new(),
+            });
+        }
+
+        let formatted_path = path.replace("/_", "").replace(".html", "");
+        let request: Vec<&str> = formatted_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        if request.is_empty() {
+            return None;
+        }
+
+        self.best_match(&request, true)
+            .or_else(|| self.best_match(&request, false))
+    }
+
+    fn best_match(&self, request: &[&str], exact: bool) -> Option<RouteMatch> {
+        self.routes
+            .iter()
+            .filter(|route| route.exact == exact && !route.segments.is_empty())
+            .filter_map(|route| route.matches(request))
+            .max_by(|a, b| a.priority(b))
+            .map(|candidate| RouteMatch {
+                path: candidate.route.path.clone(),
+                params: candidate.params,
+            })
+    }
+}
+
+/// Same table as the CmsRouterSpec of Daikoku, both sides must pick the same page
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGES: [(&str, bool); 6] = [
+        ("/apis/new", true),
+        ("/apis/[apiId]", true),
+        ("/apis/[apiId]/docs/[page]", true),
+        ("/apis", false),
+        ("/docs/[page]", false),
+        ("/docs", false),
+    ];
+
+    fn router(pages: &[(&str, bool)]) -> Router {
+        Router::new(
+            pages
+                .iter()
+                .map(|(path, exact)| Route::new(path.to_string(), *exact))
+                .collect(),
+        )
+    }
+
+    fn found(path: &str, params: &[(&str, &str)]) -> Option<RouteMatch> {
+        Some(RouteMatch {
+            path: path.to_string(),
+            params: params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    fn reversed() -> Vec<(&'static str, bool)> {
+        PAGES.iter().rev().cloned().collect()
+    }
+
+    #[test]
+    fn static_before_param() {
+        assert_eq!(router(&PAGES).find("/apis/new"), found("/apis/new", &[]));
+        assert_eq!(
+            router(&reversed()).find("/apis/new"),
+            found("/apis/new", &[])
+        );
+    }
+
+    #[test]
+    fn params_of_the_matched_page() {
+        assert_eq!(
+            router(&PAGES).find("/apis/petstore"),
+            found("/apis/[apiId]", &[("apiId", "petstore")])
+        );
+        assert_eq!(
+            router(&PAGES).find("/apis/petstore/docs/intro"),
+            found(
+                "/apis/[apiId]/docs/[page]",
+                &[("apiId", "petstore"), ("page", "intro")]
+            )
+        );
+    }
+
+    #[test]
+    fn non_exact_fallback() {
+        assert_eq!(
+            router(&PAGES).find("/apis/petstore/documentation"),
+            found("/apis", &[])
+        );
+    }
+
+    #[test]
+    fn param_before_wildcard() {
+        let expected = found("/docs/[page]", &[("page", "intro")]);
+
+        assert_eq!(router(&PAGES).find("/docs/intro"), expected);
+        assert_eq!(router(&reversed()).find("/docs/intro"), expected);
+    }
+
+    #[test]
+    fn longer_route_with_lowest_priority() {
+        assert_eq!(
+            router(&[("/apis/[apiId]/docs/[page]", true)]).find("/apis/petstore/docs"),
+            found("/apis/[apiId]/docs/[page]", &[("apiId", "petstore")])
+        );
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(router(&PAGES).find("/"), None);
+        assert_eq!(router(&PAGES).find("/unknown"), None);
+    }
+
+    #[test]
+    fn matches() {
+        let route = Route::new("/apis/[apiId]".to_string(), false);
+
+        let candidate = route.matches(&["apis", "petstore", "docs"]).unwrap();
+        assert!(candidate.complete);
+        assert_eq!(candidate.ranks, vec![2, 1, 0]);
+        assert_eq!(candidate.params.get("apiId"), Some(&"petstore".to_string()));
+
+        let candidate = route.matches(&["apis"]).unwrap();
+        assert!(!candidate.complete);
+
+        assert!(route.matches(&["docs", "petstore"]).is_none());
+        assert!(Route::new("/apis".to_string(), true)
+            .matches(&["apis", "petstore"])
+            .is_none());
+    }
+}
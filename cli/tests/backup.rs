@@ -0,0 +1,132 @@
+mod cli;
+
+use std::io::Read;
+
+use cli::commands::{
+    assets,
+    cli::{run_test, run_test_with_s3, CustomRun, CLI},
+    cms::{self, get_temporary_path},
+    environment::{self, CMS_APIKEY},
+};
+
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn restore_invalid_archive() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    CLI::build(["restore", "missing.zip"])
+        .failure()
+        .run_and_expect("failed to open missing.zip");
+
+    let archive = std::path::PathBuf::from(&path).join("invalid.zip");
+    std::fs::write(&archive, "not a zip").unwrap();
+
+    CLI::build(["restore", archive.to_str().unwrap()])
+        .failure()
+        .run_and_expect("is not a backup");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn backup_and_restore() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        let archive = std::path::PathBuf::from(&path).join("tenant.zip");
+        let archive = archive.to_str().unwrap();
+
+        CLI::build(["backup", "--out", archive]).run_and_expect("saved to");
+        CLI::build(["restore", archive, "--yes"]).run_and_expect("done");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn restore_without_project() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        let archive = std::path::PathBuf::from(&path).join("tenant.zip");
+        let archive = archive.to_str().unwrap();
+
+        CLI::build(["backup", "--out", archive]).run_and_expect("saved to");
+
+        // the restore isn't recorded in any history
+        cms::clear(true);
+
+        CLI::build_with_variables(
+            ["restore", archive, "--yes"],
+            vec![
+                ("DAIKOKU_SERVER", "http://localhost:8080"),
+                ("DAIKOKU_APIKEY", CMS_APIKEY),
+            ],
+        )
+        .run_and_expect("done");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn restore_asset_metadata() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test_with_s3(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+
+        std::fs::copy(
+            "tests/resources/daikoku.svg",
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("assets")
+                .join("daikoku.svg"),
+        )
+        .unwrap();
+
+        assets::push(
+            "daikoku.svg",
+            "R&D #1",
+            "logo + name = brand",
+            "daikoku-logo",
+        );
+
+        let archive = std::path::PathBuf::from(&path).join("tenant.zip");
+        let restored = std::path::PathBuf::from(&path).join("restored.zip");
+
+        CLI::build(["backup", "--out", archive.to_str().unwrap()]).run_and_expect("saved to");
+        CLI::build(["restore", archive.to_str().unwrap(), "--yes"]).run_and_expect("done");
+        CLI::build(["backup", "--out", restored.to_str().unwrap()]).run_and_expect("saved to");
+
+        let mut manifest = String::new();
+        zip::ZipArchive::new(std::fs::File::open(restored).unwrap())
+            .unwrap()
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+
+        assert!(manifest.contains("\"daikoku-logo\""));
+        assert!(manifest.contains("\"R&D #1\""));
+        assert!(manifest.contains("\"logo + name = brand\""));
+    })
+    .await
+}
@@ -57,6 +57,18 @@ impl CLI {
         Command::cargo_bin("daikoku").unwrap().args(args).assert()
     }
 
+    pub(crate) fn build_with_variables<I, S>(args: I, variables: Vec<(&str, &str)>) -> Assert
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<ffi::OsStr>,
+    {
+        Command::cargo_bin("daikoku")
+            .unwrap()
+            .envs(variables)
+            .args(args)
+            .assert()
+    }
+
     pub(crate) async fn start() -> Result<CLI, Box<dyn std::error::Error + 'static>> {
         let (postgres_container, daikoku_container) = Self::start_containers().await?;
 
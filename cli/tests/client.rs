@@ -0,0 +1,340 @@
+mod cli;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    thread,
+};
+
+use cli::commands::{
+    cli::{CustomRun, CLI},
+    cms::{self, get_temporary_path},
+};
+
+use serial_test::serial;
+
+/// answers each connection with the next response, then stops listening
+fn start_server(responses: Vec<(u16, &'static str)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        for (status, body) in responses {
+            let Ok((mut stream, _)) = listener.accept() else {
+                return;
+            };
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok() && line != "\r\n" && !line.is_empty() {
+                line.clear();
+            }
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Status\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+
+    address
+}
+
+/// answers with a body shorter than its content-length, then closes the connection
+fn start_truncated_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+
+    thread::spawn(move || {
+        let Ok((mut stream, _)) = listener.accept() else {
+            return;
+        };
+
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).is_ok() && line != "\r\n" && !line.is_empty() {
+            line.clear();
+        }
+
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 100\r\nconnection: close\r\n\r\n[{{"
+        );
+    });
+
+    address
+}
+
+#[tokio::test]
+#[serial]
+async fn retry_on_unavailable_server() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    let server = start_server(vec![(503, "{}"), (502, "{}"), (200, "[]")]);
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![("DAIKOKU_SERVER", &server), ("DAIKOKU_APIKEY", "apikey")],
+    )
+    .success()
+    .run_and_expect("no assets found");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn no_retry() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    let server = start_server(vec![(503, "{}"), (200, "[]")]);
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![
+            ("DAIKOKU_SERVER", &server),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_RETRIES", "0"),
+        ],
+    )
+    .code(5)
+    .run_and_expect("503");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn no_retry_of_post() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    let assets = std::path::PathBuf::from(&path).join("cms").join("assets");
+    std::fs::create_dir_all(&assets)?;
+    std::fs::write(assets.join("logo.svg"), "<svg></svg>")?;
+
+    // the asset doesn't exist, then Daikoku may have processed the upload failing with a 502
+    let server = start_server(vec![(404, "{}"), (502, "{}"), (200, "{}")]);
+
+    CLI::build_with_variables(
+        [
+            "assets", "push", "-f", "logo.svg", "-t", "logo", "-d", "logo",
+        ],
+        vec![("DAIKOKU_SERVER", &server), ("DAIKOKU_APIKEY", "apikey")],
+    )
+    .code(5)
+    .run_and_expect("[HTTP 502] : POST");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn retry_of_refused_post() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    let assets = std::path::PathBuf::from(&path).join("cms").join("assets");
+    std::fs::create_dir_all(&assets)?;
+    std::fs::write(assets.join("logo.svg"), "<svg></svg>")?;
+
+    // a 503 status means that Daikoku didn't process the upload
+    let server = start_server(vec![(404, "{}"), (503, "{}"), (200, "{}")]);
+
+    CLI::build_with_variables(
+        [
+            "assets", "push", "-f", "logo.svg", "-t", "logo", "-d", "logo",
+        ],
+        vec![("DAIKOKU_SERVER", &server), ("DAIKOKU_APIKEY", "apikey")],
+    )
+    .success()
+    .run_and_expect("New asset has been pushed");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn invalid_tls_settings() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![
+            ("DAIKOKU_SERVER", "https://localhost:1"),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_CA_FILE", "missing-ca.pem"),
+        ],
+    )
+    .failure()
+    .run_and_expect("failed to read the CA file");
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![
+            ("DAIKOKU_SERVER", "https://localhost:1"),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_CLIENT_CERT", "client.pem"),
+        ],
+    )
+    .failure()
+    .run_and_expect("client_cert and client_key must be set together");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn requests_through_proxy() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    // the server can only be reached through the proxy answering in its place
+    let proxy = start_server(vec![(200, "[]")]);
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![
+            ("DAIKOKU_SERVER", "http://daikoku.invalid"),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_RETRIES", "0"),
+            ("DAIKOKU_PROXY", &proxy),
+        ],
+    )
+    .success()
+    .run_and_expect("no assets found");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn http_error_message() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    let server = start_server(vec![(401, r#"{"error":"invalid apikey"}"#)]);
+
+    CLI::build_with_variables(
+        ["assets", "list"],
+        vec![
+            ("DAIKOKU_SERVER", &server),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_RETRIES", "0"),
+        ],
+    )
+    .code(6)
+    .run_and_multiple_expect(vec!["[HTTP 401] : GET", "invalid apikey", "daikoku login"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn http_errors_of_push_and_diff() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    for (status, code) in [(401, 6), (503, 5)] {
+        for command in ["push", "diff"] {
+            cms::clear(true);
+            let path = get_temporary_path();
+            cms::init("cms", path.clone());
+
+            std::fs::write(
+                std::path::PathBuf::from(&path)
+                    .join("cms")
+                    .join("src")
+                    .join("pages")
+                    .join("page.html"),
+                "<h1>page</h1>",
+            )?;
+
+            let server = start_server(vec![(status, "{}")]);
+
+            CLI::build_with_variables(
+                [command],
+                vec![
+                    ("DAIKOKU_SERVER", &server),
+                    ("DAIKOKU_APIKEY", "apikey"),
+                    ("DAIKOKU_RETRIES", "0"),
+                ],
+            )
+            .code(code)
+            .run_and_expect(&format!("[HTTP {}] : GET", status));
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn connection_closed_while_reading_body() -> Result<(), Box<dyn std::error::Error + 'static>>
+{
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    let server = start_truncated_server();
+
+    CLI::build_with_variables(
+        ["diff"],
+        vec![
+            ("DAIKOKU_SERVER", &server),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_RETRIES", "0"),
+        ],
+    )
+    .code(5);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn cancelled_prune() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    std::fs::write(
+        std::path::PathBuf::from(&path)
+            .join("cms")
+            .join("src")
+            .join("pages")
+            .join("page.html"),
+        "<h1>page</h1>",
+    )?;
+
+    let orphan = r#"[{"_id":"orphan","visible":true,"authenticated":false,"name":"orphan","contentType":"text/html","path":"/orphan","exact":true,"body":"","metadata":{"from":"cli"}}]"#;
+
+    // pages before the push, synchronization, then pages before the prune
+    let server = start_server(vec![(200, "[]"), (200, "{}"), (200, orphan)]);
+
+    // the confirmation reads an empty answer
+    CLI::build_with_variables(
+        ["push", "--prune"],
+        vec![
+            ("DAIKOKU_SERVER", &server),
+            ("DAIKOKU_APIKEY", "apikey"),
+            ("DAIKOKU_RETRIES", "0"),
+        ],
+    )
+    .code(9)
+    .run_and_expect("pruning cancelled");
+
+    // the pushed page is recorded even if the pruning is cancelled
+    CLI::build_with_variables(
+        ["push"],
+        vec![("DAIKOKU_SERVER", &server), ("DAIKOKU_APIKEY", "apikey")],
+    )
+    .success()
+    .run_and_expect("nothing to push");
+
+    Ok(())
+}
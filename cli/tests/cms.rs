@@ -1,7 +1,8 @@
 mod cli;
 
-use std::path::PathBuf;
+use std::{path::PathBuf, process::Command};
 
+use assert_cmd::{assert::OutputAssertExt, cargo::CommandCargoExt};
 use cli::commands::{
     cli::{run_test, CustomRun, CLI},
     cms,
@@ -90,3 +91,40 @@ async fn migrate() -> Result<(), Box<dyn std::error::Error + 'static>> {
     })
     .await
 }
+
+#[tokio::test]
+#[serial]
+async fn project_discovery() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+
+    let first = PathBuf::from(cms::get_temporary_path());
+    let second = PathBuf::from(cms::get_temporary_path());
+
+    cms::init("first", first.to_string_lossy().to_string());
+    cms::init("second", second.to_string_lossy().to_string());
+
+    let nested_folder = first.join("first").join("src").join("pages");
+
+    Command::cargo_bin("daikoku")?
+        .current_dir(&nested_folder)
+        .args(["environments", "list"])
+        .assert()
+        .success()
+        .run_and_expect(&format!("{} (current directory)", first.join("first").display()));
+
+    Command::cargo_bin("daikoku")?
+        .current_dir(&nested_folder)
+        .args(["environments", "list", "--project=second"])
+        .assert()
+        .success()
+        .run_and_expect(&format!("{} (--project)", second.join("second").display()));
+
+    Command::cargo_bin("daikoku")?
+        .current_dir(std::env::temp_dir())
+        .args(["environments", "list"])
+        .assert()
+        .success()
+        .run_and_expect(&format!("{} (default project)", second.join("second").display()));
+
+    Ok(())
+}
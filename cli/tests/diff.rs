@@ -0,0 +1,80 @@
+mod cli;
+
+use cli::commands::{
+    cli::{run_test, CustomRun, CLI},
+    cms::{self, get_temporary_path},
+    environment,
+};
+
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn diff() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        cms::init("cms", get_temporary_path());
+
+        environment::add("prod", "localhost");
+        CLI::build(["diff"]).run_and_expect("added");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn diff_after_push() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        cms::init("cms", get_temporary_path());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+        CLI::build(["push", "--dry_run=true"]).run_and_expect("nothing to push");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn duplicate_ids() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    let pages = std::path::PathBuf::from(&path).join("cms").join("src").join("pages");
+    std::fs::write(pages.join("first.html"), "---\nid: shared\n---\nfirst")?;
+    std::fs::write(pages.join("second.html"), "---\nid: shared\n---\nsecond")?;
+
+    CLI::build(["diff"]).run_and_multiple_expect(vec!["Duplicate id", "shared is used by"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn renamed_page_with_id() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        let pages = std::path::PathBuf::from(&path)
+            .join("cms")
+            .join("src")
+            .join("pages");
+        std::fs::write(pages.join("before.html"), "---\nid: renamed\n---\nrenamed").unwrap();
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        std::fs::rename(pages.join("before.html"), pages.join("after.html")).unwrap();
+
+        CLI::build(["push"]).run_and_expect("synchronization done");
+
+        // the remote page has moved to /after, no page is left at /before
+        CLI::build(["diff"]).run_and_expect("nothing to push");
+        CLI::build(["push", "--prune", "--dry_run=true"]).run_and_expect("no remote page to prune");
+    })
+    .await
+}
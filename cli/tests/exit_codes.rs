@@ -0,0 +1,67 @@
+mod cli;
+
+use cli::commands::{
+    cli::{CustomRun, CLI},
+    cms::{self, get_temporary_path},
+};
+
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn help_lists_exit_codes() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    CLI::build(["--help"])
+        .success()
+        .run_and_multiple_expect(vec!["Exit codes:", "5  network error", "9  aborted"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn exit_code_per_category() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    CLI::build(["promote", "--from", "staging", "--to", "prod"])
+        .code(3)
+        .run_and_expect("environment staging not found");
+
+    CLI::build(["restore", "missing.zip"])
+        .code(4)
+        .run_and_expect("failed to open missing.zip");
+
+    // the confirmation reads an empty answer
+    CLI::build(["environments", "clear"])
+        .code(9)
+        .run_and_expect("clearing cancelled");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn invalid_arguments() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    CLI::build(["push", "--unknown"]).code(2);
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn dry_run_spellings() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    // push and promote accept the same flag, the missing environment fails after parsing
+    for dry_run in ["-d", "--dry_run", "--dry-run", "--dry_run=true", "--dry-run=true"] {
+        CLI::build(["promote", "--from", "staging", "--to", "prod", dry_run])
+            .code(3)
+            .run_and_expect("environment staging not found");
+
+        CLI::build_with_variables(["push", dry_run], vec![("DAIKOKU_ENVIRONMENT", "staging")])
+            .code(3);
+    }
+
+    Ok(())
+}
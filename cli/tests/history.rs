@@ -0,0 +1,91 @@
+mod cli;
+
+use cli::commands::{
+    cli::{run_test, CustomRun, CLI},
+    cms::{self, get_temporary_path},
+    environment,
+};
+
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn empty_history() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    CLI::build(["history"]).run_and_expect("no push recorded yet");
+    CLI::build(["rollback", "20240101-000000-000"])
+        .failure()
+        .run_and_expect("snapshot 20240101-000000-000 not found");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn rollback() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        std::fs::write(
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("src")
+                .join("pages")
+                .join("404.html"),
+            "<h1>broken</h1>",
+        )
+        .unwrap();
+        CLI::run(["push"]);
+
+        let output = CLI::build(["history", "--output=json"]).get_output().clone();
+        let snapshots: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let first_push = snapshots[1]["id"].as_str().unwrap().to_string();
+
+        CLI::build(["rollback", &first_push, "--yes"])
+            .run_and_expect(&format!("rollback to {} done", first_push));
+        CLI::build(["push"]).run_and_expect("synchronization done");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn rollback_after_prune() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        std::fs::remove_file(
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("src")
+                .join("pages")
+                .join("404.html"),
+        )
+        .unwrap();
+        CLI::build(["push", "--prune", "--yes"]).run_and_expect("synchronization done");
+
+        let output = CLI::build(["history", "--output=json"]).get_output().clone();
+        let snapshots: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(snapshots[0]["pruned"], 1);
+        let prune = snapshots[0]["id"].as_str().unwrap().to_string();
+
+        let output = CLI::build(["rollback", &prune, "--yes"])
+            .success()
+            .get_output()
+            .clone();
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("/404"));
+    })
+    .await
+}
@@ -1,7 +1,7 @@
 mod cli;
 
 use cli::commands::{
-    cli::{run_test, CLI},
+    cli::{run_test, CustomRun, CLI},
     cms::{self, get_temporary_path},
     environment,
 };
@@ -33,3 +33,13 @@ async fn login() -> Result<(), Box<dyn std::error::Error + 'static>> {
     })
     .await
 }
+
+#[tokio::test]
+#[serial]
+async fn login_in_non_interactive_mode() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    CLI::build(["login", "--non-interactive"])
+        .failure()
+        .run_and_expect("non-interactive mode");
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+mod cli;
+
+use cli::commands::{
+    cli::{run_test, CustomRun, CLI},
+    cms::{self, get_temporary_path},
+    environment,
+};
+
+use serial_test::serial;
+
+#[tokio::test]
+#[serial]
+async fn unknown_environments() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    cms::clear(true);
+    cms::init("cms", get_temporary_path());
+
+    CLI::build(["promote", "--from", "staging", "--to", "staging"])
+        .failure()
+        .run_and_expect("the source and target environments must be different");
+    CLI::build(["promote", "--from", "staging", "--to", "prod"])
+        .failure()
+        .run_and_expect("environment staging not found");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn promote() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        environment::add("staging", "localhost");
+        CLI::run(["push"]);
+
+        std::fs::write(
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("src")
+                .join("pages")
+                .join("promoted.html"),
+            "<h1>promoted</h1>",
+        )
+        .unwrap();
+        CLI::run(["push"]);
+
+        CLI::build(["promote", "--from", "staging", "--to", "prod", "--dry-run"])
+            .run_and_expect("[dry_run] promotion done");
+        CLI::build(["promote", "--from", "staging", "--to", "prod", "--yes"])
+            .run_and_expect("nothing to promote");
+    })
+    .await
+}
@@ -79,3 +79,39 @@ async fn pull_mails() -> Result<(), Box<dyn std::error::Error + 'static>> {
     })
     .await
 }
+
+#[tokio::test]
+#[serial]
+async fn pull_pages() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+        environment::add("prod", "localhost");
+
+        CLI::run(["push"]);
+
+        let page = PathBuf::from(&path)
+            .join("cms")
+            .join("src")
+            .join("pages")
+            .join("page.html");
+
+        std::fs::write(&page, "local content").unwrap();
+
+        CLI::run(["pull", "pages"]);
+        assert!(std::fs::read_to_string(&page).unwrap() == "local content");
+
+        CLI::run(["pull", "pages", "--conflict=remote"]);
+        assert!(PathBuf::from(&path)
+            .join("cms")
+            .join("src")
+            .join("pages")
+            .join("page.html.remote")
+            .exists());
+
+        CLI::run(["pull", "pages", "--conflict=overwrite"]);
+        assert!(std::fs::read_to_string(&page).unwrap() != "local content");
+    })
+    .await
+}
@@ -3,7 +3,7 @@ mod cli;
 use cli::commands::{
     cli::{run_test, CustomRun, CLI},
     cms::{self, get_temporary_path},
-    environment,
+    environment::{self, CMS_APIKEY},
 };
 
 use serial_test::serial;
@@ -71,3 +71,93 @@ async fn push_specific_path() -> Result<(), Box<dyn std::error::Error + 'static>
     })
     .await
 }
+
+#[tokio::test]
+#[serial]
+async fn push_only_changed_pages() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        cms::init("cms", get_temporary_path());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+        CLI::build(["push"]).run_and_expect("nothing to push");
+        CLI::build(["push", "--full"]).run_and_expect("synchronization done");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn push_with_environment_variables() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        cms::init("cms", get_temporary_path());
+        environment::clear(true);
+
+        CLI::build_with_variables(
+            ["push", "--yes"],
+            vec![
+                ("DAIKOKU_SERVER", "http://localhost:8080"),
+                ("DAIKOKU_APIKEY", CMS_APIKEY),
+            ],
+        )
+        .success();
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn prune() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+
+        std::fs::remove_file(
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("src")
+                .join("pages")
+                .join("404.html"),
+        )
+        .unwrap();
+
+        CLI::build(["push", "--prune", "--dry_run=true"])
+            .run_and_multiple_expect(vec!["- deleted", "/404"]);
+        CLI::build(["push", "--prune", "--yes"]).run_and_expect("synchronization done");
+        CLI::build(["push", "--prune"]).run_and_expect("no remote page to prune");
+    })
+    .await
+}
+
+#[tokio::test]
+#[serial]
+async fn push_pulled_pages_without_conflict() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    run_test(|_| {
+        cms::clear(true);
+        let path = get_temporary_path();
+        cms::init("cms", path.clone());
+
+        environment::add("prod", "localhost");
+        CLI::run(["push"]);
+        CLI::run(["pull", "pages", "--conflict=overwrite"]);
+
+        std::fs::write(
+            std::path::PathBuf::from(&path)
+                .join("cms")
+                .join("src")
+                .join("pages")
+                .join("404.html"),
+            "<h1>Not found</h1>",
+        )
+        .unwrap();
+
+        CLI::build(["push"]).run_and_expect("synchronization done");
+    })
+    .await
+}
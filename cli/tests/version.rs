@@ -10,3 +10,33 @@ async fn version() -> Result<(), Box<dyn std::error::Error + 'static>> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn json_version() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let output = CLI::run(["version", "--output=json"]).get_output().stdout.clone();
+
+    let version: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert!(version["version"] == env!("CARGO_PKG_VERSION"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn json_error() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let output = CLI::build(["promote", "--from", "prod", "--to", "prod", "--output=json"])
+        .failure()
+        .get_output()
+        .stdout
+        .clone();
+
+    // a single document, parsable as a whole
+    let error: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    assert!(error["success"] == false);
+    assert!(error["exit_code"] == 3);
+
+    Ok(())
+}
@@ -0,0 +1,418 @@
+mod cli;
+
+use std::{
+    path::PathBuf,
+    process::{Child, Command},
+    time::Duration,
+};
+
+use assert_cmd::{assert::OutputAssertExt, cargo::CommandCargoExt};
+use cli::commands::cms::{self, get_temporary_path};
+use futures_util::StreamExt;
+
+use serial_test::serial;
+
+fn init_offline_project() -> PathBuf {
+    cms::clear(true);
+    let path = get_temporary_path();
+    cms::init("cms", path.clone());
+
+    let project = PathBuf::from(&path).join("cms");
+
+    std::fs::create_dir_all(project.join("src").join("blocks")).unwrap();
+    std::fs::write(
+        project.join("src").join("blocks").join("header.html"),
+        "<header>{{tenant.name}}</header>",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("src").join("pages").join("page.html"),
+        "_exact: true\n---\n{{> header}}{{#daikoku-apis}}<li>{{name}}</li>{{/daikoku-apis}}",
+    )
+    .unwrap();
+    std::fs::write(
+        project.join("fixtures.json"),
+        r#"{ "tenant": { "name": "Evil corp" }, "daikoku-apis": [{ "name": "api" }] }"#,
+    )
+    .unwrap();
+
+    project
+}
+
+async fn start_offline_watch(port: &str) -> (Child, String) {
+    start_offline_watch_with_args(port, &[]).await
+}
+
+async fn start_offline_watch_with_args(port: &str, args: &[&str]) -> (Child, String) {
+    let watcher = Command::cargo_bin("daikoku")
+        .unwrap()
+        .env("WATCHING_PORT", port)
+        .args(["watch", "--offline"])
+        .args(args)
+        .spawn()
+        .unwrap();
+
+    for _ in 0..20 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        if let Ok(response) = reqwest::get(format!("http://localhost:{}/", port)).await {
+            return (watcher, response.text().await.unwrap());
+        }
+    }
+
+    panic!("watch server not started")
+}
+
+#[tokio::test]
+#[serial]
+async fn offline() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    init_offline_project();
+
+    let (mut watcher, page) = start_offline_watch("3399").await;
+
+    watcher.kill()?;
+
+    assert!(page.contains("<header>Evil corp</header>"));
+    assert!(page.contains("<li>api</li>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn live_reload() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    let (mut watcher, page) = start_offline_watch("3398").await;
+
+    assert!(page.contains("/__daikoku/live-reload"));
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async("ws://localhost:3398/__daikoku/live-reload").await?;
+
+    std::fs::write(
+        project.join("src").join("styles").join("main.css"),
+        "body { color: red; }",
+    )?;
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next()).await;
+
+    watcher.kill()?;
+
+    let change: serde_json::Value =
+        serde_json::from_str(&message?.unwrap()?.into_text()?).unwrap();
+
+    assert!(change["kind"] == "css");
+    assert!(change["path"] == "styles/main.css");
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn updated_pages() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    let (mut watcher, _) = start_offline_watch("3397").await;
+
+    std::fs::write(
+        project.join("src").join("pages").join("page.html"),
+        "_exact: true\n---\n<h1>updated</h1>",
+    )?;
+    std::fs::write(
+        project.join("src").join("pages").join("new.html"),
+        "<h1>new page</h1>",
+    )?;
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let page = reqwest::get("http://localhost:3397/").await?.text().await?;
+    let new_page = reqwest::get("http://localhost:3397/new").await?.text().await?;
+
+    watcher.kill()?;
+
+    assert!(page.contains("<h1>updated</h1>"));
+    assert!(new_page.contains("<h1>new page</h1>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn routes() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+    let pages = project.join("src").join("pages");
+
+    for (file, content) in [
+        ("apis/page.html", "apis"),
+        ("apis/new/page.html", "_exact: true\n---\nnew api"),
+        ("apis/[apiId]/page.html", "_exact: true\n---\napi {{apiId}}"),
+        ("docs/page.html", "docs"),
+        ("docs/guides/page.html", "guides"),
+        (
+            "teams/[teamId]/apis/[apiId]/page.html",
+            "_exact: true\n---\nteam {{teamId}} api {{apiId}}",
+        ),
+    ] {
+        let path = pages.join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, content)?;
+    }
+
+    let (mut watcher, _) = start_offline_watch("3396").await;
+
+    let mut results = vec![];
+    for (path, expected) in [
+        ("/apis", "apis"),
+        ("/apis/new", "new api"),
+        ("/apis/petstore", "api petstore"),
+        ("/apis/petstore/documentation", "apis"),
+        ("/docs/getting-started", "docs"),
+        ("/docs/guides/first-api", "guides"),
+        ("/teams/my-team/apis/petstore", "team my-team api petstore"),
+        ("/unknown/page", "404 page not found"),
+    ] {
+        let page = reqwest::get(format!("http://localhost:3396{}", path))
+            .await?
+            .text()
+            .await?;
+        results.push((path, expected, page));
+    }
+
+    watcher.kill()?;
+
+    for (path, expected, page) in results {
+        assert!(
+            page.trim_start().starts_with(expected),
+            "{} served {:?} instead of {:?}",
+            path,
+            page,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn front_matter() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    std::fs::write(
+        project.join("src").join("pages").join("page.html"),
+        "---\n_exact: true\ntitle: Home\n---\n<p>first</p>\n---\n<p>second</p>",
+    )?;
+
+    let (mut watcher, page) = start_offline_watch("3395").await;
+
+    watcher.kill()?;
+
+    assert!(page.starts_with("<p>first</p>\n---\n<p>second</p>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn text_before_separator() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    std::fs::write(
+        project.join("src").join("pages").join("page.html"),
+        "Note: read this\n---\n<p>content</p>",
+    )?;
+
+    let (mut watcher, page) = start_offline_watch("3389").await;
+
+    watcher.kill()?;
+
+    assert!(page.starts_with("Note: read this\n---\n<p>content</p>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn non_interactive() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    init_offline_project();
+
+    let (mut watcher, page) = start_offline_watch_with_args("3390", &["--yes"]).await;
+
+    watcher.kill()?;
+
+    assert!(page.contains("<header>Evil corp</header>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn invalid_front_matter() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    std::fs::write(
+        project.join("src").join("pages").join("page.html"),
+        "---\ntitle: Home\n_exact: maybe\n---\n<h1>home</h1>",
+    )?;
+
+    Command::cargo_bin("daikoku")?
+        .env("WATCHING_PORT", "3394")
+        .args(["watch", "--offline"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "page.html:3 : invalid metadata, _exact must be true or false",
+        ));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn invalid_files() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    std::fs::write(project.join("src").join("pages").join("logo.png"), [0x89, 0x50, 0x4e, 0x47])?;
+    std::fs::write(project.join("src").join("pages").join("latin.html"), [0xe9, 0x74, 0xe9])?;
+
+    Command::cargo_bin("daikoku")?
+        .env("WATCHING_PORT", "3393")
+        .args(["watch", "--offline"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("logo.png : unsupported .png file"))
+        .stderr(predicates::str::contains("latin.html : stream did not contain valid UTF-8"))
+        .stderr(predicates::str::contains("2 invalid file(s) in the project"));
+
+    let (mut watcher, page) = start_offline_watch_with_args("3393", &["--skip-invalid"]).await;
+
+    watcher.kill()?;
+
+    assert!(page.contains("<header>Evil corp</header>"));
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn daikokuignore() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+    let pages = project.join("src").join("pages");
+
+    std::fs::create_dir_all(pages.join("drafts"))?;
+    std::fs::write(pages.join("drafts").join("page.html"), "draft")?;
+    std::fs::write(pages.join("scratch.wip.html"), "scratch")?;
+    std::fs::write(pages.join("keep.wip.html"), "kept")?;
+    // would be an invalid file if it was read
+    std::fs::write(pages.join("logo.png"), [0x89, 0x50, 0x4e, 0x47])?;
+    std::fs::write(
+        project.join(".daikoku").join(".daikokuignore"),
+        "# drafts are never published\nsrc/pages/drafts/\n*.wip.html\n!keep.wip.html\n**/*.png\n",
+    )?;
+
+    let (mut watcher, _) = start_offline_watch("3392").await;
+
+    let mut results = vec![];
+    for (path, expected) in [
+        ("/drafts", "404 page not found"),
+        ("/scratch.wip", "404 page not found"),
+        ("/keep.wip", "kept"),
+    ] {
+        let page = reqwest::get(format!("http://localhost:3392{}", path))
+            .await?
+            .text()
+            .await?;
+        results.push((path, expected, page));
+    }
+
+    watcher.kill()?;
+
+    for (path, expected, page) in results {
+        assert!(
+            page.starts_with(expected),
+            "{} served {:?} instead of {:?}",
+            path,
+            page,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn legacy_daikokuignore() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+    let pages = project.join("src").join("pages");
+
+    std::fs::create_dir_all(pages.join("drafts"))?;
+    std::fs::write(pages.join("drafts").join("page.html"), "draft")?;
+    std::fs::write(pages.join("old.html"), "old")?;
+    std::fs::write(pages.join("kept.html"), "kept")?;
+    std::fs::write(
+        project.join(".daikoku").join(".daikokuignore"),
+        "/drafts\npages/old.html\n",
+    )?;
+
+    let (mut watcher, _) = start_offline_watch("3388").await;
+
+    let mut results = vec![];
+    for (path, expected) in [
+        ("/drafts", "404 page not found"),
+        ("/old", "404 page not found"),
+        ("/kept", "kept"),
+    ] {
+        let page = reqwest::get(format!("http://localhost:3388{}", path))
+            .await?
+            .text()
+            .await?;
+        results.push((path, expected, page));
+    }
+
+    watcher.kill()?;
+
+    for (path, expected, page) in results {
+        assert!(
+            page.starts_with(expected),
+            "{} served {:?} instead of {:?}",
+            path,
+            page,
+            expected
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+#[serial]
+async fn project_manifest() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let project = init_offline_project();
+
+    std::fs::rename(project.join("src"), project.join("web"))?;
+    std::fs::rename(
+        project.join("web").join("blocks"),
+        project.join("web").join("components"),
+    )?;
+    std::fs::write(project.join("web").join("pages").join("private.html"), "private")?;
+    std::fs::write(
+        project.join("daikoku.toml"),
+        "ignore = [\"web/pages/private.html\"]\n\n[sources]\nroot = \"web\"\nblocks = \"components\"\n",
+    )?;
+
+    let (mut watcher, page) = start_offline_watch("3391").await;
+
+    let private_page = reqwest::get("http://localhost:3391/private")
+        .await?
+        .text()
+        .await?;
+
+    watcher.kill()?;
+
+    assert!(page.contains("<header>Evil corp</header>"));
+    assert!(private_page.starts_with("404 page not found"));
+
+    Ok(())
+}
@@ -135,6 +135,17 @@ class CmsApiController(
         }
     }
 
+  def deleteCmsPage(id: String) =
+    CmsApiAction.async { ctx =>
+      env.dataStore.cmsRepo
+        .forTenant(ctx.tenant)
+        .deleteById(id)
+        .map {
+          case true  => Ok(Json.obj("done" -> true))
+          case false => NotFound(Json.obj("error" -> "cms page not found"))
+        }
+    }
+
   def sync() =
     CmsApiAction.async(parse.json) { ctx =>
       for {
@@ -175,9 +186,18 @@ class CmsApiController(
                       .save(page.toCmsPage(ctx.tenant.id))
                   )
               } else {
+                // a page keeping its id after a rename replaces the page stored at its former
+                // path, and a page taking an id replaces the page stored at its path
                 env.dataStore.cmsRepo
                   .forTenant(ctx.tenant)
-                  .deleteById(page.id())
+                  .delete(
+                    Json.obj(
+                      "$or" -> Json.arr(
+                        Json.obj("_id" -> page.id()),
+                        Json.obj("path" -> page.path())
+                      )
+                    )
+                  )
                   .map(_ =>
                     env.dataStore.cmsRepo
                       .forTenant(ctx.tenant)
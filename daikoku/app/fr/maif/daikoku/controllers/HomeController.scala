@@ -17,7 +17,12 @@ import fr.maif.daikoku.env.Env
 import fr.maif.daikoku.services.{CmsPage, CmsRequestRendering}
 import fr.maif.daikoku.storage.drivers.postgres.PostgresDataStore
 import fr.maif.daikoku.utils.future.EnhancedObject
-import fr.maif.daikoku.utils.{Errors, OtoroshiClient, S3Configuration}
+import fr.maif.daikoku.utils.{
+  CmsRouter,
+  Errors,
+  OtoroshiClient,
+  S3Configuration
+}
 import org.apache.pekko.http.scaladsl.util.FastFuture
 import org.apache.pekko.stream.connectors.s3.BucketAccess
 import play.api.i18n.{I18nSupport, MessagesApi}
@@ -25,9 +30,7 @@ import play.api.libs
 import play.api.libs.json.*
 import play.api.mvc.*
 
-import scala.collection.mutable
 import scala.concurrent.{ExecutionContext, Future}
-import scala.util.matching.Regex
 
 enum ServiceStatus(val value: String):
   case Up extends ServiceStatus("UP")
@@ -259,84 +262,12 @@ class HomeController(
       path: String,
       cmsPaths: Seq[(String, CmsPage)],
       strictMode: Boolean = false
-  ): (Seq[CmsPage], Map[String, JsValue]) = {
-    val paths = path
-      .replace("/_", "")
-      .split("/")
-      .filter(_.nonEmpty)
-
-    if (paths.isEmpty)
-      (Seq(), Map.empty)
-    else {
-      var matched = false
-
-      val formatted_paths: Seq[(Array[String], CmsPage)] = cmsPaths
-        .map(r =>
-          (
-            r._1.replace("/_/", "").split("/") ++ Array(
-              if (r._2.exact) "" else "*"
-            ),
-            r._2
-          )
-        )
-        .map(p => (p._1.filter(_.nonEmpty), p._2))
-        .filter(p => p._1.nonEmpty)
-        .sortBy(_._1.length)
-
-      val params = mutable.Map[String, JsValue]()
-
-      (
-        paths
-          .foldLeft(
-            formatted_paths
-          ) { (paths, path) =>
-            {
-              if (paths.isEmpty || matched)
-                paths
-              else {
-                val matchingRoutes: Seq[(Array[String], CmsPage)] =
-                  paths.filter(p => {
-                    if (p._1.isEmpty) {
-                      false
-                    } else {
-                      val path_path = p._1.head
-                      if (path_path == path || path_path == "*") {
-                        true
-                      } else {
-                        val pattern = new Regex("\\[\\w+\\]")
-
-                        pattern.findFirstMatchIn(path_path) match {
-                          case Some(matched) =>
-                            val key = matched.matched
-                              .substring(1, matched.matched.length - 1)
-                            params += (key -> JsString(path))
-
-                            true
-                          case None =>
-                            false
-                        }
-                      }
-                    }
-                  })
-
-                if (matchingRoutes.nonEmpty)
-                  matchingRoutes.map(p => (p._1.tail, p._2))
-                else {
-                  val matchingRoute = paths.find(p => p._1.isEmpty)
-                  if (matchingRoute.nonEmpty && !strictMode) {
-                    matched = true
-                    Seq(matchingRoute.get)
-                  } else
-                    Seq()
-                }
-              }
-            }
-          }
-          .map(_._2),
-        params.toMap
-      )
+  ): (Seq[CmsPage], Map[String, JsValue]) =
+    CmsRouter.bestMatch(path, cmsPaths, exact = strictMode) match {
+      case Some((page, params)) =>
+        (Seq(page), params.map { case (key, value) => key -> JsString(value) })
+      case None => (Seq(), Map.empty)
     }
-  }
 
   def renderCmsPageFromBody(path: String) =
     DaikokuUnauthenticatedAction.async(parse.json) { ctx =>
@@ -0,0 +1,148 @@
+package fr.maif.daikoku.utils
+
+import scala.annotation.tailrec
+
+/** Routing of the CMS pages which aren't found by their path. The same rules
+  * are applied by `daikoku watch` (cli/src/router.rs), so that previews serve
+  * the same page as production.
+  *
+  * Segments are compared from left to right: a static segment wins over a
+  * `[param]` segment, which wins over the end of a non exact page. Routes
+  * covering the whole request win over routes longer than the request, and the
+  * path breaks the remaining ties.
+  */
+object CmsRouter {
+
+  private val paramPattern = "\\[(\\w+)\\]".r
+
+  private sealed trait Segment {
+    def rank: Int
+  }
+
+  /** end of a non exact page, matching any number of segments */
+  private case object Wildcard extends Segment {
+    val rank = 0
+  }
+
+  private case class Param(name: String) extends Segment {
+    val rank = 1
+  }
+
+  private case class Static(value: String) extends Segment {
+    val rank = 2
+  }
+
+  private case class Candidate[T](
+      path: String,
+      page: T,
+      params: Map[String, String],
+      complete: Boolean,
+      ranks: Seq[Int]
+  )
+
+  private def parse(segment: String): Segment =
+    paramPattern.findFirstMatchIn(segment) match {
+      case Some(matched) => Param(matched.group(1))
+      case None          => Static(segment)
+    }
+
+  private def segments(path: String, exact: Boolean): Seq[Segment] = {
+    val parsed = path
+      .replace("/_/", "")
+      .split("/")
+      .toSeq
+      .filter(_.nonEmpty)
+      .map(parse)
+
+    if (exact) parsed else parsed :+ Wildcard
+  }
+
+  private def matches[T](
+      path: String,
+      page: T,
+      exact: Boolean,
+      request: Seq[String]
+  ): Option[Candidate[T]] = {
+    val route = segments(path, exact)
+
+    @tailrec
+    def loop(
+        index: Int,
+        params: Map[String, String],
+        ranks: Seq[Int]
+    ): Option[Candidate[T]] =
+      if (index == request.length) {
+        val remaining = route.drop(index)
+        Some(
+          Candidate(
+            path,
+            page,
+            params,
+            remaining.isEmpty || remaining == Seq(Wildcard),
+            ranks
+          )
+        )
+      } else
+        route.lift(index) match {
+          case None => None
+          case Some(Wildcard) =>
+            Some(
+              Candidate(
+                path,
+                page,
+                params,
+                complete = true,
+                ranks ++ request.drop(index).map(_ => Wildcard.rank)
+              )
+            )
+          case Some(segment @ Param(name)) =>
+            loop(
+              index + 1,
+              params + (name -> request(index)),
+              ranks :+ segment.rank
+            )
+          case Some(segment @ Static(value)) if value == request(index) =>
+            loop(index + 1, params, ranks :+ segment.rank)
+          case Some(_) => None
+        }
+
+    if (route.isEmpty) None else loop(0, Map.empty, Seq.empty)
+  }
+
+  private def compareRanks(a: Seq[Int], b: Seq[Int]): Int =
+    a.zip(b)
+      .map { case (left, right) => left.compare(right) }
+      .find(_ != 0)
+      .getOrElse(a.length.compare(b.length))
+
+  private def priority[T](a: Candidate[T], b: Candidate[T]): Int = {
+    val complete = a.complete.compare(b.complete)
+    if (complete != 0) complete
+    else {
+      val ranks = compareRanks(a.ranks, b.ranks)
+      if (ranks != 0) ranks else b.path.compare(a.path)
+    }
+  }
+
+  /** Page of the request among the exact pages, or among the non exact ones,
+    * with the values of its `[param]` segments
+    */
+  def bestMatch[T](
+      requestPath: String,
+      pages: Seq[(String, T)],
+      exact: Boolean
+  ): Option[(T, Map[String, String])] = {
+    val request = requestPath
+      .replace("/_", "")
+      .split("/")
+      .toSeq
+      .filter(_.nonEmpty)
+
+    if (request.isEmpty) None
+    else
+      pages
+        .flatMap { case (path, page) => matches(path, page, exact, request) }
+        .reduceOption((a, b) => if (priority(a, b) >= 0) a else b)
+        .map(candidate => (candidate.page, candidate.params))
+  }
+}
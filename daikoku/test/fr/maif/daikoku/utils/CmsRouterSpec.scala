@@ -0,0 +1,73 @@
+package fr.maif.daikoku.utils
+
+import org.scalatestplus.play.PlaySpec
+
+/** Same table as the tests of cli/src/router.rs, both sides must pick the same
+  * page
+  */
+class CmsRouterSpec extends PlaySpec {
+
+  private val pages = Seq(
+    ("/apis/new", true),
+    ("/apis/[apiId]", true),
+    ("/apis/[apiId]/docs/[page]", true),
+    ("/apis", false),
+    ("/docs/[page]", false),
+    ("/docs", false)
+  )
+
+  private def find(
+      path: String,
+      pages: Seq[(String, Boolean)] = pages
+  ): Option[(String, Map[String, String])] = {
+    def routes(exact: Boolean) =
+      pages.filter(_._2 == exact).map { case (path, _) => (path, path) }
+
+    CmsRouter
+      .bestMatch(path, routes(exact = true), exact = true)
+      .orElse(CmsRouter.bestMatch(path, routes(exact = false), exact = false))
+  }
+
+  "bestMatch" should {
+    "prefer a static segment to a param" in {
+      find("/apis/new") mustBe Some(("/apis/new", Map.empty))
+      find("/apis/new", pages.reverse) mustBe Some(("/apis/new", Map.empty))
+    }
+
+    "extract the params of the matched page only" in {
+      find("/apis/petstore") mustBe Some(
+        ("/apis/[apiId]", Map("apiId" -> "petstore"))
+      )
+      find("/apis/petstore/docs/intro") mustBe Some(
+        (
+          "/apis/[apiId]/docs/[page]",
+          Map("apiId" -> "petstore", "page" -> "intro")
+        )
+      )
+    }
+
+    "fall back to the non exact pages" in {
+      find("/apis/petstore/documentation") mustBe Some(("/apis", Map.empty))
+    }
+
+    "prefer a param to the end of a non exact page" in {
+      find("/docs/intro") mustBe Some(("/docs/[page]", Map("page" -> "intro")))
+      find("/docs/intro", pages.reverse) mustBe Some(
+        ("/docs/[page]", Map("page" -> "intro"))
+      )
+    }
+
+    "match a longer page with the lowest priority" in {
+      val longer = Seq(("/apis/[apiId]/docs/[page]", true))
+
+      find("/apis/petstore/docs", longer) mustBe Some(
+        ("/apis/[apiId]/docs/[page]", Map("apiId" -> "petstore"))
+      )
+    }
+
+    "not match the root" in {
+      find("/") mustBe None
+      find("/unknown") mustBe None
+    }
+  }
+}